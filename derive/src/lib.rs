@@ -0,0 +1,222 @@
+//! This crate contains the derive macros of the [`deferred-reference`](https://docs.rs/deferred-reference) crate.
+//! These are re-exported by the `deferred-reference` crate when its `soa` feature is enabled, so this crate
+//! should not be used directly. This crate has no dependencies, so the macros parse their input by hand.
+
+#![deny(missing_docs)]
+#![forbid(clippy::missing_docs_in_private_items)]
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, Spacing, TokenStream, TokenTree};
+
+/// A named field of a struct.
+struct Field {
+    /// The `#[cfg(...)]` attributes of the field, which are forwarded to the generated code for the field.
+    cfg: String,
+    /// The visibility of the field, e.g. `pub(crate)`, or an empty string if the field is private.
+    vis: String,
+    /// The name of the field.
+    name: String,
+    /// The type of the field.
+    ty: String,
+}
+
+/// A struct with named fields.
+struct Struct {
+    /// The visibility of the struct.
+    vis: String,
+    /// The name of the struct.
+    name: String,
+    /// The named fields of the struct.
+    fields: Vec<Field>,
+}
+
+/// Returns `true` if `token` is the punctuation character `ch`.
+fn is_punct(token: &TokenTree, ch: char) -> bool {
+    matches!(token, TokenTree::Punct(punct) if punct.as_char() == ch)
+}
+
+/// Skips the outer attributes (including doc comments) at the start of `tokens` and returns
+/// the `#[cfg(...)]` attributes among them with the remaining tokens.
+fn parse_attributes(tokens: &[TokenTree]) -> (String, &[TokenTree]) {
+    let mut tokens = tokens;
+    let mut cfg = String::new();
+    while let [pound, TokenTree::Group(attribute), rest @ ..] = tokens {
+        if !is_punct(pound, '#') {
+            break;
+        }
+        if matches!(attribute.stream().into_iter().next(), Some(TokenTree::Ident(ident)) if ident.to_string() == "cfg") {
+            cfg += &format!("#{} ", attribute);
+        }
+        tokens = rest;
+    }
+    (cfg, tokens)
+}
+
+/// Parses the visibility at the start of `tokens` and returns it with the remaining tokens.
+fn parse_visibility(tokens: &[TokenTree]) -> (String, &[TokenTree]) {
+    match tokens {
+        [TokenTree::Ident(ident), TokenTree::Group(group), rest @ ..]
+            if ident.to_string() == "pub" && group.delimiter() == Delimiter::Parenthesis =>
+        {
+            (format!("pub {}", group), rest)
+        }
+        [TokenTree::Ident(ident), rest @ ..] if ident.to_string() == "pub" => (String::from("pub"), rest),
+        _ => (String::new(), tokens),
+    }
+}
+
+/// Splits `tokens` at the commas that are not nested inside of angle brackets.
+fn split_fields(tokens: &[TokenTree]) -> Vec<&[TokenTree]> {
+    let mut fields = Vec::new();
+    let (mut start, mut depth) = (0, 0usize);
+    for (index, token) in tokens.iter().enumerate() {
+        if is_punct(token, '<') {
+            depth += 1;
+        } else if is_punct(token, '>') {
+            // the `>` of an arrow `->` does not close an angle bracket
+            let arrow = index > 0 && matches!(&tokens[index - 1], TokenTree::Punct(punct)
+                if punct.as_char() == '-' && punct.spacing() == Spacing::Joint);
+            if !arrow {
+                depth = depth.saturating_sub(1);
+            }
+        } else if is_punct(token, ',') && depth == 0 {
+            fields.push(&tokens[start..index]);
+            start = index + 1;
+        }
+    }
+    if start < tokens.len() {
+        fields.push(&tokens[start..]);
+    }
+    fields
+}
+
+/// Parses a named field, e.g. `pub position: [f32; 3]`.
+fn parse_field(tokens: &[TokenTree]) -> Result<Field, &'static str> {
+    let (cfg, tokens) = parse_attributes(tokens);
+    let (vis, tokens) = parse_visibility(tokens);
+    match tokens {
+        [TokenTree::Ident(name), colon, ty @ ..] if is_punct(colon, ':') && !ty.is_empty() => Ok(Field {
+            cfg,
+            vis,
+            name: name.to_string(),
+            ty: ty.iter().cloned().collect::<TokenStream>().to_string(),
+        }),
+        _ => Err("expected a named field"),
+    }
+}
+
+/// Parses a struct with named fields and without generic parameters.
+fn parse_struct(input: TokenStream) -> Result<Struct, &'static str> {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    let (vis, tokens) = parse_visibility(parse_attributes(&tokens).1);
+    let (name, body) = match tokens {
+        [TokenTree::Ident(keyword), TokenTree::Ident(name), rest @ ..] if keyword.to_string() == "struct" => (name, rest),
+        _ => return Err("SoaRecord can only be derived for structs"),
+    };
+    let body = match body {
+        [TokenTree::Group(group)] if group.delimiter() == Delimiter::Brace => group.stream(),
+        [first, ..] if is_punct(first, '<') => return Err("SoaRecord can not be derived for generic structs"),
+        _ => return Err("SoaRecord can only be derived for structs with named fields"),
+    };
+    let tokens: Vec<TokenTree> = body.into_iter().collect();
+    let fields = split_fields(&tokens).into_iter().map(parse_field).collect::<Result<Vec<_>, _>>()?;
+    if fields.is_empty() {
+        return Err("SoaRecord can not be derived for structs without fields");
+    }
+    Ok(Struct { vis, name: name.to_string(), fields })
+}
+
+/// Generates the column structs and the `SoaRecord` implementation for `record`.
+fn generate(record: &Struct) -> String {
+    let Struct { vis, name, fields } = record;
+    let mut columns = String::new();
+    let mut columns_ref = String::new();
+    let mut columns_mut = String::new();
+    let mut with_capacity = String::new();
+    let mut write = String::new();
+    let mut read = String::new();
+    let mut make_ref = String::new();
+    let mut make_mut = String::new();
+    let mut drop_in_place = String::new();
+    // the `#[cfg(...)]` attributes of a field are repeated on everything that is generated for the field
+    for Field { cfg, vis: field_vis, name: field, ty } in fields {
+        columns += &format!(
+            "{2} #[doc = \"The column of the `{0}` field.\"] {0}: ::deferred_reference::SoaColumn<{1}>,",
+            field, ty, cfg,
+        );
+        columns_ref += &format!(
+            "{3} #[doc = \"A deferred reference to the column of the `{0}` field.\"] {1} {0}: ::deferred_reference::Deferred<&'a [{2}]>,",
+            field, field_vis, ty, cfg,
+        );
+        columns_mut += &format!(
+            "{3} #[doc = \"A deferred mutable reference to the column of the `{0}` field.\"] {1} {0}: ::deferred_reference::Deferred<&'a mut [{2}]>,",
+            field, field_vis, ty, cfg,
+        );
+        with_capacity += &format!("{1} {0}: ::deferred_reference::SoaColumn::with_capacity(capacity),", field, cfg);
+        write += &format!("{1} columns.{0}.write(index, record.{0});", field, cfg);
+        read += &format!("{1} {0}: columns.{0}.read(index),", field, cfg);
+        make_ref += &format!("{1} {0}: columns.{0}.slice(len),", field, cfg);
+        make_mut += &format!("{1} {0}: columns.{0}.slice_mut(len),", field, cfg);
+        drop_in_place += &format!("{1} columns.{0}.drop_in_place(len);", field, cfg);
+    }
+    // note: the bodies of the unsafe methods are wrapped in `unsafe` blocks for crates that enable the
+    // `unsafe_op_in_unsafe_fn` lint, which makes these blocks unused in the other crates.
+    format!(
+        "#[doc = \"The columns of a [Soa](::deferred_reference::Soa) of [`{name}`], with one column per field.\"]
+        {vis} struct {name}Columns {{ {columns} }}
+
+        #[doc = \"Deferred references to the columns of a [Soa](::deferred_reference::Soa) of [`{name}`].\"]
+        {vis} struct {name}ColumnsRef<'a> {{ {columns_ref} }}
+
+        #[doc = \"Deferred mutable references to the columns of a [Soa](::deferred_reference::Soa) of [`{name}`].\"]
+        {vis} struct {name}ColumnsMut<'a> {{ {columns_mut} }}
+
+        #[allow(unused_unsafe)]
+        unsafe impl ::deferred_reference::SoaRecord for {name} {{
+            type Columns = {name}Columns;
+            type ColumnsRef<'a> = {name}ColumnsRef<'a>;
+            type ColumnsMut<'a> = {name}ColumnsMut<'a>;
+
+            fn with_capacity(capacity: usize) -> Self::Columns {{ {name}Columns {{ {with_capacity} }} }}
+
+            unsafe fn write(columns: &mut Self::Columns, index: usize, record: Self) {{ unsafe {{ {write} }} }}
+
+            unsafe fn read(columns: &mut Self::Columns, index: usize) -> Self {{ unsafe {{ Self {{ {read} }} }} }}
+
+            unsafe fn columns(columns: &Self::Columns, len: usize) -> Self::ColumnsRef<'_> {{
+                unsafe {{ {name}ColumnsRef {{ {make_ref} }} }}
+            }}
+
+            unsafe fn columns_mut(columns: &Self::Columns, len: usize) -> Self::ColumnsMut<'_> {{
+                unsafe {{ {name}ColumnsMut {{ {make_mut} }} }}
+            }}
+
+            unsafe fn drop_in_place(columns: &mut Self::Columns, len: usize) {{ unsafe {{ {drop_in_place} }} }}
+        }}",
+        vis = vis,
+        name = name,
+        columns = columns,
+        columns_ref = columns_ref,
+        columns_mut = columns_mut,
+        with_capacity = with_capacity,
+        write = write,
+        read = read,
+        make_ref = make_ref,
+        make_mut = make_mut,
+        drop_in_place = drop_in_place,
+    )
+}
+
+/// Derives the `SoaRecord` trait of the `deferred-reference` crate for a struct with named fields, so that the struct
+/// can be stored in a `Soa` container. For a struct `Name`, this also generates the structs `NameColumns` (which stores
+/// the `SoaColumn` of each field), `NameColumnsRef<'a>` and `NameColumnsMut<'a>` (which hold deferred references to all columns at once).
+/// See the documentation of `Soa` in the `deferred-reference` crate for an example.
+#[proc_macro_derive(SoaRecord)]
+pub fn derive_soa_record(input: TokenStream) -> TokenStream {
+    let code = match parse_struct(input) {
+        Ok(record) => generate(&record),
+        Err(message) => format!("::core::compile_error!(\"{}\");", message),
+    };
+    code.parse().expect("SoaRecord: generated invalid code")
+}
@@ -0,0 +1,142 @@
+//! This module contains the [ByAddress] wrapper, which compares deferred references by address.
+
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
+
+use crate::{Deferred, Reference};
+
+/// A wrapper which implements [Eq], [Hash] and [Ord] for a [Deferred] based on the location that it points to,
+/// instead of on the value that it points to. For deferred slices, the length of the slice is taken into account
+/// as well (after the address). None of these implementations dereference the wrapped [Deferred], so they
+/// never create any references to the pointee. This makes it possible to use deferred references as keys in
+/// maps and sets, even while other deferred mutable references to the same pointees are being dereferenced.
+///
+/// The comparison traits implemented directly on [Deferred] compare by value, just like references do.
+/// See also [Deferred::ptr_eq].
+///
+/// # Example
+/// ```
+/// use deferred_reference::{ByAddress, DeferMut};
+/// use core::cell::UnsafeCell;
+/// use std::collections::HashSet;
+/// let buffer = UnsafeCell::new([0u8; 4]);
+/// let mut deferred = unsafe { buffer.defer_mut() };
+/// // note: splitting the array (rather than a slice) works without the `slice_ptr_len` feature, too.
+/// let (left, right) = deferred.split_at_mut(2);
+/// let mut scheduled = HashSet::new();
+/// assert!(scheduled.insert(ByAddress(left)));
+/// assert!(scheduled.insert(ByAddress(right))); // equal by value, but not by address
+/// assert_eq!(2, scheduled.len());
+/// ```
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub struct ByAddress<P>(pub P);
+
+impl<P> ByAddress<P> {
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> P {
+        self.0
+    }
+}
+
+impl<T: Reference> From<Deferred<T>> for ByAddress<Deferred<T>> {
+    fn from(deferred: Deferred<T>) -> Self {
+        ByAddress(deferred)
+    }
+}
+
+impl<T: Reference> PartialEq for ByAddress<Deferred<T>> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.ptr_eq(&other.0)
+    }
+}
+
+impl<T: Reference> Eq for ByAddress<Deferred<T>> {}
+
+impl<T: Reference> PartialOrd for ByAddress<Deferred<T>> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Reference> Ord for ByAddress<Deferred<T>> {
+    #[allow(ambiguous_wide_pointer_comparisons)] // comparing the metadata is intended
+    fn cmp(&self, other: &Self) -> Ordering {
+        // raw pointers compare by address first and by metadata (i.e. the length of slices) second
+        self.0.as_ptr().cmp(&other.0.as_ptr())
+    }
+}
+
+impl<T: Reference> Hash for ByAddress<Deferred<T>> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // raw pointers hash both the address and the metadata (i.e. the length of slices)
+        self.0.as_ptr().hash(state)
+    }
+}
+
+impl<T: Reference> core::fmt::Debug for ByAddress<Deferred<T>> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("ByAddress").field(&self.0.as_ptr()).finish()
+    }
+}
+
+// note: all of these tests need the length of deferred slices.
+#[cfg(all(test, feature = "slice_ptr_len"))]
+mod tests {
+    use alloc::collections::BTreeMap;
+    use core::cell::UnsafeCell;
+    use crate::{ByAddress, Defer, DeferMut, Deferred};
+
+    #[test]
+    fn ptr_eq() {
+        let buffer = UnsafeCell::new([0u8; 8]);
+        let deferred = buffer.defer();
+        let deferred_mut = unsafe { buffer.defer_mut() };
+        assert!(deferred.ptr_eq(&deferred_mut));
+        let slice: Deferred<&[u8]> = deferred.into();
+        let (left, right) = slice.split_at(4);
+        assert!(!left.ptr_eq(&right));
+        // same address, different length
+        assert!(!slice.ptr_eq(&slice.split_at(4).0));
+        assert!(slice.ptr_eq(&slice.split_at(8).0));
+    }
+
+    #[test]
+    fn by_address() {
+        let buffer = UnsafeCell::new([7u32; 8]);
+        let mut deferred: Deferred<&mut [u32]> = unsafe { buffer.defer_mut() }.into();
+        let (mut left, right) = deferred.split_at_mut(4);
+        let left_ref = unsafe { left.clone_unchecked() }.into_ref();
+        let (a, b) = left_ref.split_at(2);
+        let mut map = BTreeMap::new();
+        map.insert(ByAddress(right.into_ref()), "right");
+        map.insert(ByAddress(a), "a");
+        map.insert(ByAddress(b), "b");
+        map.insert(ByAddress(left_ref), "left");
+        assert_eq!(4, map.len());
+        // ordered by address first, then by length
+        assert_eq!(alloc::vec!["a", "left", "b", "right"], map.values().copied().collect::<alloc::vec::Vec<_>>());
+        // the pointees can be mutated while the keys are in use, because the keys never dereference
+        left[0] = 1;
+        assert_eq!(Some(&"a"), map.get(&ByAddress(a)));
+        assert_ne!(ByAddress(a), ByAddress(b));
+        assert_eq!(ByAddress(a), ByAddress(a));
+    }
+
+    #[test]
+    fn by_value() {
+        let buffer = UnsafeCell::new([1u8, 2, 1, 2]);
+        let deferred: Deferred<&[u8]> = buffer.defer().into();
+        let (left, right) = deferred.split_at(2);
+        assert_eq!(left, right);
+        assert!(left <= right);
+        let mut deferred_mut = unsafe { buffer.defer_mut() };
+        deferred_mut[3] = 3;
+        let (left, right) = deferred.split_at(2);
+        assert_ne!(left, right);
+        assert!(left < right);
+        assert_eq!(deferred_mut, buffer.defer());
+        assert_eq!("[1, 2]", format!("{:?}", left));
+        assert!(format!("{:?}", ByAddress(left)).starts_with("ByAddress("));
+    }
+}
@@ -1,5 +1,7 @@
 //! This module contains trait implementations from the core library for [Deferred].
 
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
 use core::ops::{Deref, DerefMut, Index, IndexMut};
 
 use crate::{Deferred, Reference, SliceLike, SlicePointerIndex};
@@ -63,6 +65,74 @@ impl<T: ?Sized> core::fmt::Pointer for Deferred<&mut T> {
     }
 }
 
+/// Compares the pointees by value, just like the implementations on `&T` and `&mut T` do, which dereferences
+/// both deferred references. Use [Deferred::ptr_eq] or [ByAddress](crate::ByAddress) to compare by address instead.
+/// The comparison itself does not need the length of deferred slices, but obtaining
+/// deferred subslices to compare (e.g. with `split_at` or indexing) panics on stable Rust
+/// without the `slice_ptr_len` feature, see [PointerLength](crate::PointerLength).
+impl<T, U> PartialEq<Deferred<U>> for Deferred<T>
+where
+    T: Reference,
+    U: Reference,
+    T::Target: PartialEq<U::Target>,
+{
+    fn eq(&self, other: &Deferred<U>) -> bool {
+        PartialEq::eq(self.deref(), other.deref())
+    }
+}
+
+/// Deferred references are [Eq] if their pointees are, see the [PartialEq] implementation.
+impl<T> Eq for Deferred<T>
+where
+    T: Reference,
+    T::Target: Eq,
+{}
+
+/// Compares the pointees by value, with the same caveats for deferred slices as the [PartialEq] implementation.
+impl<T, U> PartialOrd<Deferred<U>> for Deferred<T>
+where
+    T: Reference,
+    U: Reference,
+    T::Target: PartialOrd<U::Target>,
+{
+    fn partial_cmp(&self, other: &Deferred<U>) -> Option<Ordering> {
+        PartialOrd::partial_cmp(self.deref(), other.deref())
+    }
+}
+
+/// Orders the pointees by value, see the [PartialOrd] implementation.
+impl<T> Ord for Deferred<T>
+where
+    T: Reference,
+    T::Target: Ord,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        Ord::cmp(self.deref(), other.deref())
+    }
+}
+
+/// Hashes the pointee by value, consistent with the [PartialEq] implementation. Use [ByAddress](crate::ByAddress)
+/// to hash by address instead.
+impl<T> Hash for Deferred<T>
+where
+    T: Reference,
+    T::Target: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.deref().hash(state)
+    }
+}
+
+impl<T> core::fmt::Debug for Deferred<T>
+where
+    T: Reference,
+    T::Target: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self.deref(), f)
+    }
+}
+
 // requires #![feature(coerce_unsized)]:
 // this doesn't work, compiler panicks: error: internal compiler error: compiler/rustc_mir/src/monomorphize/collector.rs:884:22: unexpected unsized tail: [u8; 1024]
 // impl<T, const N: usize> CoerceUnsized<Deferred<&[T]>> for Deferred<&[T; N]> {}
@@ -247,6 +317,18 @@ mod tests {
             #[cfg(feature = "slice_ptr_len")]
             assert_eq!(&mut 0, canary);
         }
+        #[test]
+        #[should_panic(expected = "index 4 out of range for slice pointer of length 4")]
+        fn index_at_len() {
+            let buffer = [0u8; 4];
+            let _ = Deferred::from(&buffer)[4];
+        }
+        #[test]
+        #[should_panic(expected = "index 4 out of range for slice pointer of length 4")]
+        fn index_mut_at_len() {
+            let mut buffer = [0u8; 4];
+            Deferred::from(&mut buffer)[4] = 1;
+        }
     }
 
     /// tests for the `From` trait
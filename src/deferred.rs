@@ -1,6 +1,6 @@
 use core::ptr::NonNull;
 
-use crate::Reference;
+use crate::{PointerError, PointerLayout, Reference};
 
 /// A smart pointer which holds a "deferred reference" to an instance of type `T: ?Sized`.
 /// It has all the properties of a normal reference (`&T` or `&mut T`),
@@ -56,7 +56,7 @@ where
 /// 1. Through the [Deferred::new] method.
 /// 2. Through the [From]/[Into] traits implemented for `Deferred<&T>`.
 /// 3. Through the [Defer::defer](crate::Defer::defer) method on types that implement the [Defer](crate::Defer) trait.
-/// 4. Through the _unsafe_ [Deferred::from_raw] method.
+/// 4. Through the _unsafe_ [Deferred::from_raw_checked] or [Deferred::from_raw] methods, or [Deferred::from_raw_parts] for slices.
 /// 5. Through the _extremely unsafe_ [`defer`](macro@defer) macro (not recommended).
 impl<'a, T: ?Sized> Deferred<&'a T> {
     /// Construct a new deferred immutable reference from an existing immutable reference.
@@ -91,6 +91,12 @@ impl<'a, T: ?Sized> Deferred<&'a T> {
     /// methods such as [`<Deferred as Deref>::deref`](core::ops::Deref::deref). This would
     /// alias the entire memory region and is only safe if there are no writers at the same time.
     ///
+    /// # Panics
+    /// In debug builds, this method panics if `ptr` is null. With the `layout_for_ptr` feature, it also panics
+    /// if `ptr` is not properly aligned or if the size of the pointee exceeds `isize::MAX` bytes. Without this
+    /// feature only the null check is performed (even for sized `T`), use [Deferred::from_raw_checked] instead
+    /// to validate the alignment and the size on stable Rust.
+    ///
     /// # Caveat
     /// The lifetime for the returned [Deferred] is inferred from its usage. To prevent accidental misuse,
     /// it's suggested to tie the lifetime to whichever source lifetime is safe in the context, such as
@@ -118,6 +124,14 @@ impl<'a, T: ?Sized> Deferred<&'a T> {
     /// let deferred = unsafe { Deferred::from_raw(core::ptr::addr_of!(buffer)) };
     /// ```
     pub unsafe fn from_raw(ptr: *const T) -> Self {
+        // note: `T: ?Sized` rules out `PointerLayout`, so the alignment and the size are only checked
+        // with the `layout_for_ptr` feature, see `Deferred::from_raw_checked` for the stable alternative.
+        if cfg!(debug_assertions) {
+            // SAFETY: the caller promises that the pointer is dereferenceable, so its metadata is valid.
+            if let Err(error) = PointerError::check_raw(ptr) {
+                panic!("Deferred::from_raw: {}", error);
+            }
+        }
         // note: this method must live in the impl for `&'a T`
         // otherwise Rust can't infer the type properly.
         Self {
@@ -128,6 +142,78 @@ impl<'a, T: ?Sized> Deferred<&'a T> {
             ptr: NonNull::new_unchecked(ptr as *mut T),
         }
     }
+
+    /// Construct a new deferred immutable reference to an instance of `T: ?Sized` from a raw pointer,
+    /// after checking that the pointer is non-null, that it is properly aligned for `T` and that the
+    /// size of the pointee does not exceed `isize::MAX` bytes. None of these checks dereference the pointer.
+    /// This is useful at FFI boundaries, where pointers are received from foreign code.
+    ///
+    /// # Errors
+    /// Returns a [PointerError] describing the first check that failed.
+    ///
+    /// # Safety
+    /// The checks performed by this method only cover part of the invariant of [Deferred]. The caller
+    /// must still uphold all of the other safety requirements of [Deferred::from_raw], i.e. the pointer
+    /// must be dereferenceable, point to initialized memory and remain valid for as long as the returned
+    /// `Deferred` exists.
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    /// Use [Deferred::from_raw_parts] to construct deferred slices on stable Rust.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::{Deferred, PointerError};
+    /// let buffer = [0u32; 4];
+    /// // SAFETY: `buffer` is not moved or mutably aliased after this.
+    /// let deferred = unsafe { Deferred::from_raw_checked(buffer.as_ptr()) };
+    /// assert!(deferred.is_ok());
+    /// let unaligned = (buffer.as_ptr() as *const u8).wrapping_add(1) as *const u32;
+    /// let deferred = unsafe { Deferred::from_raw_checked(unaligned) };
+    /// assert_eq!(Some(PointerError::Unaligned), deferred.err());
+    /// ```
+    pub unsafe fn from_raw_checked(ptr: *const T) -> Result<Self, PointerError>
+    where
+        T: PointerLayout,
+    {
+        PointerError::check(ptr)?;
+        // SAFETY: the caller upholds the remaining guarantees of `from_raw`.
+        Ok(Self::from_raw(ptr))
+    }
+}
+
+impl<'a, T> Deferred<&'a [T]> {
+    /// Construct a new deferred immutable reference to a slice from a pointer and a length,
+    /// without creating an intermediate reference to the slice. This is a shorthand for calling
+    /// [Deferred::from_raw] on the result of [`core::ptr::slice_from_raw_parts`]. Because the length is
+    /// passed in explicitly, constructing the deferred slice works on stable Rust, too (i.e. without the
+    /// `slice_ptr_len` feature). Methods which need the length of the deferred slice (such as indexing)
+    /// still panic without this feature, so on stable Rust the deferred slice needs to be dereferenced first.
+    ///
+    /// # Safety
+    /// The caller must uphold the same guarantees as for [Deferred::from_raw], which means that
+    /// `ptr` must be non-null, properly aligned and valid for reads of `len` consecutive
+    /// initialized elements of type `T` for as long as the returned `Deferred` exists. The total
+    /// size of the slice (`len * size_of::<T>()`) may not exceed `isize::MAX`. These conditions
+    /// are checked in debug builds, but not in release builds.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let buffer = [1u8, 2, 3, 4];
+    /// // SAFETY: `buffer` is not moved or mutably aliased after this.
+    /// let deferred: Deferred<&[u8]> = unsafe { Deferred::from_raw_parts(buffer.as_ptr(), 4) };
+    /// // dereferencing before indexing makes this work without the `slice_ptr_len` feature, too
+    /// assert_eq!(3, (*deferred)[2]);
+    /// ```
+    pub unsafe fn from_raw_parts(ptr: *const T, len: usize) -> Deferred<&'a [T]> {
+        if cfg!(debug_assertions) {
+            if let Err(error) = PointerError::check_parts(ptr, len) {
+                panic!("Deferred::from_raw_parts: {}", error);
+            }
+        }
+        Self::from_raw(core::ptr::slice_from_raw_parts(ptr, len))
+    }
 }
 
 /// # Constructors for deferred _mutable_ references
@@ -136,7 +222,7 @@ impl<'a, T: ?Sized> Deferred<&'a T> {
 /// 1. Through the [Deferred::new_mut] method.
 /// 2. Through the [From]/[Into] traits implemented for `Deferred<&mut T>`.
 /// 3. Through the _unsafe_ [DeferMut::defer_mut](crate::DeferMut::defer_mut) method on types that implement the [DeferMut](crate::DeferMut) trait.
-/// 4. Through the _unsafe_ [Deferred::from_raw_mut] method.
+/// 4. Through the _unsafe_ [Deferred::from_raw_mut_checked] or [Deferred::from_raw_mut] methods, or [Deferred::from_raw_parts_mut] for slices.
 /// 5. Through the _extremely unsafe_ [`defer_mut`](macro@defer_mut) macro (not recommended).
 impl<'a, T: ?Sized> Deferred<&'a mut T> {
     /// Construct a new deferred mutable reference from an existing mutable reference.
@@ -167,6 +253,12 @@ impl<'a, T: ?Sized> Deferred<&'a mut T> {
     /// [`<Deferred as DerefMut>::deref_mut`](core::ops::DerefMut::deref_mut). This would alias the entire memory
     /// region and is only safe when there are no other writers and readers, respectively.
     ///
+    /// # Panics
+    /// In debug builds, this method panics if `ptr` is null. With the `layout_for_ptr` feature, it also panics
+    /// if `ptr` is not properly aligned or if the size of the pointee exceeds `isize::MAX` bytes. Without this
+    /// feature only the null check is performed (even for sized `T`), use [Deferred::from_raw_mut_checked]
+    /// instead to validate the alignment and the size on stable Rust.
+    ///
     /// # Caveat
     /// The lifetime for the returned `Deferred` is inferred from its usage. To prevent accidental misuse,
     /// it's suggested to tie the lifetime to whichever source lifetime is safe in the context, such as
@@ -196,10 +288,92 @@ impl<'a, T: ?Sized> Deferred<&'a mut T> {
     /// The documentation of [DeferMut](crate::DeferMut) contains some additional examples of how to properly call
     /// [Deferred::from_raw_mut].
     pub unsafe fn from_raw_mut(ptr: *mut T) -> Deferred<&'a mut T> {
+        // note: `T: ?Sized` rules out `PointerLayout`, so the alignment and the size are only checked
+        // with the `layout_for_ptr` feature, see `Deferred::from_raw_mut_checked` for the stable alternative.
+        if cfg!(debug_assertions) {
+            // SAFETY: the caller promises that the pointer is dereferenceable, so its metadata is valid.
+            if let Err(error) = PointerError::check_raw(ptr) {
+                panic!("Deferred::from_raw_mut: {}", error);
+            }
+        }
         Self {
             ptr: NonNull::new_unchecked(ptr as *mut T),
         }
     }
+
+    /// Construct a new deferred mutable reference to an instance of `T: ?Sized` from a raw pointer,
+    /// after checking that the pointer is non-null, that it is properly aligned for `T` and that the
+    /// size of the pointee does not exceed `isize::MAX` bytes. None of these checks dereference the pointer.
+    /// This is useful at FFI boundaries, where pointers are received from foreign code.
+    ///
+    /// # Errors
+    /// Returns a [PointerError] describing the first check that failed.
+    ///
+    /// # Safety
+    /// The checks performed by this method only cover part of the invariant of [Deferred]. The caller
+    /// must still uphold all of the other safety requirements of [Deferred::from_raw_mut], i.e. the pointer
+    /// must be dereferenceable, point to initialized memory, remain valid for as long as the returned
+    /// `Deferred` exists and no references to the pointee may exist when the `Deferred` is constructed.
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    /// Use [Deferred::from_raw_parts_mut] to construct deferred slices on stable Rust.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::{Deferred, PointerError};
+    /// let mut value = 0u32;
+    /// // SAFETY: `value` is not moved or aliased after this.
+    /// let mut deferred = unsafe { Deferred::from_raw_mut_checked(&mut value as *mut u32) }.unwrap();
+    /// *deferred = 42;
+    /// assert_eq!(42, value);
+    /// let deferred = unsafe { Deferred::from_raw_mut_checked(core::ptr::null_mut::<u32>()) };
+    /// assert_eq!(Some(PointerError::Null), deferred.err());
+    /// ```
+    pub unsafe fn from_raw_mut_checked(ptr: *mut T) -> Result<Self, PointerError>
+    where
+        T: PointerLayout,
+    {
+        PointerError::check(ptr)?;
+        // SAFETY: the caller upholds the remaining guarantees of `from_raw_mut`.
+        Ok(Self::from_raw_mut(ptr))
+    }
+}
+
+impl<'a, T> Deferred<&'a mut [T]> {
+    /// Construct a new deferred mutable reference to a slice from a pointer and a length,
+    /// without creating an intermediate reference to the slice. This is a shorthand for calling
+    /// [Deferred::from_raw_mut] on the result of [`core::ptr::slice_from_raw_parts_mut`]. Because the length
+    /// is passed in explicitly, constructing the deferred slice works on stable Rust, too (i.e. without the
+    /// `slice_ptr_len` feature). Methods which need the length of the deferred slice (such as indexing)
+    /// still panic without this feature, so on stable Rust the deferred slice needs to be dereferenced first.
+    ///
+    /// # Safety
+    /// The caller must uphold the same guarantees as for [Deferred::from_raw_mut], which means that
+    /// `ptr` must be non-null, properly aligned and valid for reads and writes of `len` consecutive
+    /// initialized elements of type `T` for as long as the returned `Deferred` exists and that no
+    /// references to these elements exist when the `Deferred` is constructed. The total size of the
+    /// slice (`len * size_of::<T>()`) may not exceed `isize::MAX`. These conditions are checked in
+    /// debug builds, but not in release builds.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let mut buffer = [0u8; 4];
+    /// // SAFETY: `buffer` is not moved or aliased after this.
+    /// let mut deferred: Deferred<&mut [u8]> = unsafe { Deferred::from_raw_parts_mut(buffer.as_mut_ptr(), 4) };
+    /// // dereferencing before indexing makes this work without the `slice_ptr_len` feature, too
+    /// (*deferred)[2] = 42;
+    /// assert_eq!([0, 0, 42, 0], buffer);
+    /// ```
+    pub unsafe fn from_raw_parts_mut(ptr: *mut T, len: usize) -> Deferred<&'a mut [T]> {
+        if cfg!(debug_assertions) {
+            if let Err(error) = PointerError::check_parts(ptr, len) {
+                panic!("Deferred::from_raw_parts_mut: {}", error);
+            }
+        }
+        Self::from_raw_mut(core::ptr::slice_from_raw_parts_mut(ptr, len))
+    }
 }
 
 /// # Methods available on all deferred references
@@ -214,6 +388,28 @@ where
         self.ptr.as_ptr() as *const _
     }
 
+    /// Returns `true` if both deferred references point to the same location, without dereferencing
+    /// them. For deferred slices this also compares the lengths. This works across deferred immutable
+    /// and deferred mutable references. Note that the comparison operators `==` and `!=` on [Deferred]
+    /// compare the values that the deferred references point to instead. See also [ByAddress](crate::ByAddress).
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let buffer = [0u8; 4];
+    /// let deferred = Deferred::new(&buffer);
+    /// let (left, right) = deferred.split_at(2);
+    /// assert_eq!(*left, *right); // the values are equal...
+    /// assert!(!left.ptr_eq(&right)); // ...but the pointers are not
+    /// assert!(left.ptr_eq(&deferred.split_at(2).0));
+    /// ```
+    pub fn ptr_eq<U>(&self, other: &Deferred<U>) -> bool
+    where
+        U: Reference<Target = T::Target>,
+    {
+        core::ptr::eq(self.as_ptr(), other.as_ptr())
+    }
+
     /// Unsizes the deferred reference. This method is experimental.
     ///
     /// # Example
@@ -289,7 +485,8 @@ impl<'a, T: ?Sized> Deferred<&'a mut T> {
 #[cfg(test)]
 mod tests {
     use core::cell::UnsafeCell;
-    use crate::{Defer, DeferMut, Deferred};
+    use core::ops::DerefMut;
+    use crate::{Defer, DeferMut, Deferred, PointerError};
 
     #[test]
     fn new() {
@@ -367,5 +564,94 @@ mod tests {
         assert_eq!(deferred.as_mut_ptr() as usize, buffer.get() as usize);
     }
 
-    
+    #[test]
+    fn from_raw_parts() {
+        let buffer = [1u16, 2, 3, 4];
+        let deferred = unsafe { Deferred::from_raw_parts(buffer.as_ptr(), 3) };
+        assert_eq!(buffer.as_ptr() as usize, deferred.as_ptr() as *const u16 as usize);
+        assert_eq!(&[1, 2, 3], &*deferred);
+    }
+
+    #[test]
+    fn from_raw_parts_mut() {
+        let mut buffer = [1u16, 2, 3, 4];
+        let mut deferred = unsafe { Deferred::from_raw_parts_mut(buffer.as_mut_ptr().add(1), 3) };
+        let mut deferred2 = unsafe { deferred.clone_unchecked() };
+        // the explicit `deref_mut` makes this test work without the `slice_ptr_len` feature, too
+        deferred.deref_mut()[0] = 42;
+        deferred2.deref_mut()[2] = 43;
+        assert_eq!([1, 42, 3, 43], buffer);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "non-null")]
+    fn from_raw_parts_null() {
+        let _ = unsafe { Deferred::from_raw_parts(core::ptr::null::<u16>(), 0) };
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "aligned")]
+    fn from_raw_parts_mut_unaligned() {
+        let mut buffer = [0u16; 4];
+        let ptr = unsafe { (buffer.as_mut_ptr() as *mut u8).add(1) as *mut u16 };
+        let _ = unsafe { Deferred::from_raw_parts_mut(ptr, 2) };
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "isize::MAX")]
+    fn from_raw_parts_too_large() {
+        let buffer = [0u16; 4];
+        let _ = unsafe { Deferred::from_raw_parts(buffer.as_ptr(), usize::MAX / 2) };
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "non-null")]
+    fn from_raw_null() {
+        let _ = unsafe { Deferred::from_raw(core::ptr::null::<u16>()) };
+    }
+
+    #[cfg(all(debug_assertions, feature = "layout_for_ptr"))]
+    #[test]
+    #[should_panic(expected = "aligned")]
+    fn from_raw_mut_unaligned_slice() {
+        let mut buffer = [0u16; 4];
+        let ptr = unsafe { (buffer.as_mut_ptr() as *mut u8).add(1) as *mut u16 };
+        let _ = unsafe { Deferred::from_raw_mut(core::ptr::slice_from_raw_parts_mut(ptr, 2)) };
+    }
+
+    #[test]
+    fn from_raw_checked() {
+        let buffer = [0u32; 4];
+        let deferred = unsafe { Deferred::from_raw_checked(buffer.as_ptr()) }.unwrap();
+        assert_eq!(0, *deferred);
+        let unaligned = (buffer.as_ptr() as *const u8).wrapping_add(2) as *const u32;
+        assert_eq!(Some(PointerError::Unaligned), unsafe { Deferred::from_raw_checked(unaligned) }.err());
+        assert_eq!(Some(PointerError::Null), unsafe { Deferred::from_raw_checked(core::ptr::null::<u32>()) }.err());
+    }
+
+    #[cfg(feature = "slice_ptr_len")]
+    #[test]
+    fn from_raw_checked_slice() {
+        let buffer = [0u32; 4];
+        let slice_ptr = core::ptr::slice_from_raw_parts(buffer.as_ptr(), 4);
+        let deferred = unsafe { Deferred::from_raw_checked(slice_ptr) }.unwrap();
+        assert_eq!(4, deferred.len());
+        let huge = core::ptr::slice_from_raw_parts(buffer.as_ptr(), usize::MAX / 4);
+        assert_eq!(Some(PointerError::TooLarge), unsafe { Deferred::from_raw_checked(huge) }.err());
+    }
+
+    #[test]
+    fn from_raw_mut_checked() {
+        let mut buffer = [0u32; 4];
+        let mut deferred = unsafe { Deferred::from_raw_mut_checked(&mut buffer as *mut [u32; 4]) }.unwrap();
+        deferred[1] = 42;
+        assert_eq!(Some(PointerError::Null), unsafe { Deferred::from_raw_mut_checked(core::ptr::null_mut::<u32>()) }.err());
+        let unaligned = (buffer.as_mut_ptr() as *mut u8).wrapping_add(1) as *mut u32;
+        assert_eq!(Some(PointerError::Unaligned), unsafe { Deferred::from_raw_mut_checked(unaligned) }.err());
+        assert_eq!([0, 42, 0, 0], buffer);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,207 @@
+//! This module contains the [DeferredArena] bump allocator which hands out disjoint deferred mutable references.
+
+use core::cell::Cell;
+
+use crate::{Deferred, PointerLength, SliceLike, SlicePointerIndex};
+
+/// A bump allocator over a caller-provided array or slice, which hands out disjoint deferred mutable
+/// references (i.e. `Deferred<&mut [T]>` and `Deferred<&mut T>`) to regions of the array or slice.
+///
+/// Allocation happens through a shared reference `&self`, so many regions can be allocated and used at the
+/// same time. Every region is handed out at most once (until the arena is [reset](DeferredArena::reset)),
+/// so the regions are disjoint by construction and allocation is entirely safe. The regions borrow the arena,
+/// which makes it impossible to reset the arena while any of the regions are still in use.
+///
+/// The arena does not allocate memory by itself and it does not need the `alloc` crate: the backing memory
+/// must be initialized by the caller and it is never dropped by the arena. Elements are not reinitialized
+/// when they are handed out again after a reset.
+///
+/// # Example
+/// ```
+/// use deferred_reference::{DeferMut, DeferredArena};
+/// use core::cell::UnsafeCell;
+/// let buffer = UnsafeCell::new([0u32; 16]);
+/// // SAFETY: the arena is the only one accessing `buffer` until it is reset.
+/// let mut arena = DeferredArena::new(unsafe { buffer.defer_mut() });
+/// let mut a = arena.alloc_slice(4).unwrap();
+/// let mut b = arena.alloc().unwrap();
+/// (*a)[0] = 1; // `a` and `b` are disjoint, so both can be mutated at the same time
+/// *b = 2;
+/// assert_eq!(11, arena.remaining());
+/// arena.reset(); // all regions must be out of use by now
+/// assert_eq!(16, arena.remaining());
+/// assert_eq!([1, 0, 0, 0, 2], unsafe { &*buffer.get() }[..5]);
+/// ```
+pub struct DeferredArena<'arena, T> {
+    /// The backing memory of the arena.
+    memory: Deferred<&'arena mut [T]>,
+    /// The number of elements in `memory`. This is stored separately so that the arena
+    /// works on stable Rust, too (i.e. without the `slice_ptr_len` feature).
+    capacity: usize,
+    /// The index of the first element which has not been handed out yet.
+    next: Cell<usize>,
+}
+
+impl<'arena, T> DeferredArena<'arena, T> {
+    /// Creates a new arena which hands out regions of the array or slice that `memory` points to.
+    /// The arena becomes the sole owner of `memory`, so no other (deferred) references to the backing memory
+    /// should be dereferenced for as long as the arena is in use.
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    /// Use the [From] implementation on `&mut [T]` to construct an arena over a slice on stable Rust.
+    pub fn new<S>(memory: Deferred<&'arena mut S>) -> Self
+    where
+        S: SliceLike<Element = T> + ?Sized,
+    {
+        let capacity = PointerLength::len(memory.as_ptr());
+        // SAFETY: the pointer and the length both originate from `memory`, so this is safe.
+        let memory = unsafe { Deferred::from_raw_parts_mut(memory.as_mut_ptr() as *mut T, capacity) };
+        Self {
+            memory,
+            capacity,
+            next: Cell::new(0),
+        }
+    }
+
+    /// Allocates a deferred mutable reference to a region of `len` consecutive elements, or returns `None`
+    /// if there are less than `len` elements remaining in the arena.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::DeferredArena;
+    /// let mut buffer = [0u8; 8];
+    /// let arena = DeferredArena::from(&mut buffer[..]);
+    /// let mut header = arena.alloc_slice(2).unwrap();
+    /// let mut body = arena.alloc_slice(6).unwrap();
+    /// assert!(arena.alloc_slice(1).is_none());
+    /// // dereferencing before indexing makes this work without the `slice_ptr_len` feature, too
+    /// (*header)[0] = 1;
+    /// (*body)[5] = 2;
+    /// ```
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice(&self, len: usize) -> Option<Deferred<&mut [T]>> {
+        let start = self.next.get();
+        let end = start.checked_add(len).filter(|&end| end <= self.capacity)?;
+        self.next.set(end);
+        // note: unlike `split_at_mut_unchecked`, indexing with a `Range` does not need the length of the slice,
+        // so this also works on stable Rust (i.e. without the `slice_ptr_len` feature).
+        // SAFETY: `start <= end <= capacity`, so the region is in bounds. The region `[start, end)`
+        // SAFETY: has not been handed out before (until the next reset, which requires `&mut self`).
+        unsafe { Some(Deferred::from_raw_mut((start..end).get_unchecked_mut(self.memory.as_mut_ptr()))) }
+    }
+
+    /// Allocates a deferred mutable reference to a single element, or returns `None` if the arena is full.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::DeferredArena;
+    /// let mut buffer = [0u8; 2];
+    /// let arena = DeferredArena::from(&mut buffer[..]);
+    /// let mut a = arena.alloc().unwrap();
+    /// let mut b = arena.alloc().unwrap();
+    /// assert!(arena.alloc().is_none());
+    /// core::mem::swap(&mut *a, &mut *b);
+    /// ```
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc(&self) -> Option<Deferred<&mut T>> {
+        self.alloc_slice(1).map(|region| {
+            // SAFETY: `region` has exactly one element.
+            unsafe { Deferred::from_raw_mut(region.as_mut_ptr() as *mut T) }
+        })
+    }
+
+    /// Returns the total number of elements in the arena.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of elements which have not been allocated yet.
+    pub fn remaining(&self) -> usize {
+        self.capacity - self.next.get()
+    }
+
+    /// Makes all elements available for allocation again. This requires a mutable reference to the arena,
+    /// so that none of the previously allocated regions can still be in use.
+    pub fn reset(&mut self) {
+        self.next.set(0);
+    }
+}
+
+impl<'arena, T> From<&'arena mut [T]> for DeferredArena<'arena, T> {
+    fn from(slice: &'arena mut [T]) -> Self {
+        let capacity = slice.len();
+        Self {
+            memory: Deferred::from(slice),
+            capacity,
+            next: Cell::new(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DeferredArena;
+
+    #[cfg(feature = "slice_ptr_len")]
+    #[test]
+    fn alloc() {
+        use core::cell::UnsafeCell;
+        use crate::DeferMut;
+        let buffer = UnsafeCell::new([0u16; 10]);
+        let mut arena = DeferredArena::new(unsafe { buffer.defer_mut() });
+        assert_eq!(10, arena.capacity());
+        let mut a = arena.alloc_slice(3).unwrap();
+        let mut b = arena.alloc().unwrap();
+        let mut c = arena.alloc_slice(6).unwrap();
+        assert!(arena.alloc().is_none());
+        assert!(arena.alloc_slice(usize::MAX).is_none());
+        assert_eq!(0, arena.remaining());
+        assert!(!a.overlaps(&b) && !b.overlaps(&c) && !a.overlaps(&c));
+        // the regions can be mutably dereferenced at the same time
+        let (a_ref, b_ref, c_ref) = (&mut a[..], &mut *b, &mut c[..]);
+        a_ref[2] = 1;
+        *b_ref = 2;
+        c_ref[0] = 3;
+        assert_eq!(0, arena.alloc_slice(0).unwrap().len());
+        arena.reset();
+        assert_eq!(10, arena.remaining());
+        assert_eq!([0, 0, 1, 2, 3, 0, 0, 0, 0, 0], unsafe { *buffer.get() });
+    }
+
+    #[test]
+    fn from_slice() {
+        let mut buffer = [0u8; 4];
+        {
+            let arena = DeferredArena::from(&mut buffer[..]);
+            let mut regions = [arena.alloc().unwrap(), arena.alloc().unwrap(), arena.alloc().unwrap()];
+            for (i, region) in regions.iter_mut().enumerate() {
+                **region = i as u8 + 1;
+            }
+            assert_eq!(1, arena.remaining());
+        }
+        assert_eq!([1, 2, 3, 0], buffer);
+    }
+
+    #[cfg(feature = "slice_ptr_len")]
+    #[test]
+    fn threads() {
+        extern crate std;
+        use crate::Deferred;
+        let mut buffer = [0usize; 64];
+        let arena = DeferredArena::new(Deferred::from(&mut buffer));
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let mut region = arena.alloc_slice(8).unwrap();
+                scope.spawn(move || {
+                    for j in 0..8 {
+                        region[j] = i * 8 + j;
+                    }
+                });
+            }
+        });
+        for (i, value) in buffer.iter().enumerate() {
+            assert_eq!(i, *value);
+        }
+    }
+}
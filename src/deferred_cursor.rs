@@ -0,0 +1,272 @@
+//! This module contains the [DeferredCursor] struct, which implements the [std::io] traits on deferred byte slices.
+
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+
+use crate::{Deferred, PointerLength, Reference, SliceLike, SlicePointerIndex};
+
+/// A cursor over a deferred reference to a byte slice or byte array, which implements [Read], [BufRead],
+/// [Seek] and (for `DeferredCursor<&mut [u8]>`) [Write]. Unlike [std::io::Cursor], this cursor does not
+/// hold a reference to the whole slice. Each operation only creates a reference to the bytes that it
+/// copies, so other deferred references can access other parts of the same buffer at the same time.
+/// This requires the `std` feature.
+///
+/// Just like [std::io::Cursor], the position of the cursor may be set beyond the end of the buffer.
+/// Reads at such a position return zero bytes and writes at such a position write zero bytes.
+///
+/// # Example
+/// ```
+/// use deferred_reference::DeferredCursor;
+/// use std::io::{Read, Write};
+/// let mut buffer = [0u8; 16];
+/// let (front, back) = buffer.split_at_mut(8);
+/// let mut writer = DeferredCursor::from(back);
+/// let mut reader = DeferredCursor::from(&*front);
+/// std::thread::scope(|scope| {
+///     scope.spawn(move || writer.write_all(b"deferred").unwrap());
+///     scope.spawn(move || {
+///         let mut header = [0u8; 8];
+///         reader.read_exact(&mut header).unwrap();
+///         assert_eq!([0; 8], header);
+///     });
+/// });
+/// assert_eq!(b"deferred", &buffer[8..]);
+/// ```
+pub struct DeferredCursor<T>
+where
+    T: Reference,
+    T::Target: SliceLike<Element = u8>,
+{
+    /// The deferred reference to the bytes.
+    inner: Deferred<T>,
+    /// The length of the bytes, which is stored so that it is also known on stable Rust after construction.
+    len: usize,
+    /// The position of the cursor, which may lie beyond `len`.
+    pos: u64,
+}
+
+impl<T> DeferredCursor<T>
+where
+    T: Reference,
+    T::Target: SliceLike<Element = u8>,
+{
+    /// Creates a new cursor at position 0.
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    /// Use the [From] implementations on `&[u8]` and `&mut [u8]` to construct a cursor over a slice on stable Rust.
+    pub fn new(inner: Deferred<T>) -> Self {
+        let len = PointerLength::len(inner.as_ptr());
+        Self { inner, len, pos: 0 }
+    }
+
+    /// Returns the current position of the cursor.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Sets the position of the cursor.
+    pub fn set_position(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+
+    /// Returns the length of the bytes that this cursor reads from or writes to.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the cursor has no bytes at all.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of bytes between the position of the cursor and the end of the buffer.
+    pub fn remaining(&self) -> usize {
+        self.len - self.start()
+    }
+
+    /// Consumes the cursor and returns the deferred reference to the bytes.
+    pub fn into_inner(self) -> Deferred<T> {
+        self.inner
+    }
+
+    /// Returns the position of the cursor, clamped to the length of the buffer.
+    fn start(&self) -> usize {
+        if self.pos < self.len as u64 { self.pos as usize } else { self.len }
+    }
+
+    /// Returns a reference to the `len` bytes at the position of the cursor.
+    fn slice(&self, len: usize) -> &[u8] {
+        let start = self.start();
+        debug_assert!(len <= self.len - start);
+        // SAFETY: the range lies within the buffer, so we only need to create a reference to it.
+        unsafe { &*(start..start + len).get_unchecked(self.inner.as_ptr()) }
+    }
+}
+
+impl<T> Read for DeferredCursor<T>
+where
+    T: Reference,
+    T::Target: SliceLike<Element = u8>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.remaining());
+        buf[..len].copy_from_slice(self.slice(len));
+        self.pos += len as u64;
+        Ok(len)
+    }
+}
+
+impl<T> BufRead for DeferredCursor<T>
+where
+    T: Reference,
+    T::Target: SliceLike<Element = u8>,
+{
+    /// Returns a reference to all the remaining bytes in the buffer, which is held until the next
+    /// call to [BufRead::consume].
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(self.slice(self.remaining()))
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt as u64;
+    }
+}
+
+impl<T> Seek for DeferredCursor<T>
+where
+    T: Reference,
+    T::Target: SliceLike<Element = u8>,
+{
+    fn seek(&mut self, style: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match style {
+            SeekFrom::Start(pos) => {
+                self.pos = pos;
+                return Ok(pos);
+            }
+            SeekFrom::End(offset) => (self.len as u64, offset),
+            SeekFrom::Current(offset) => (self.pos, offset),
+        };
+        let pos = if offset >= 0 {
+            base.checked_add(offset.unsigned_abs())
+        } else {
+            base.checked_sub(offset.unsigned_abs())
+        };
+        match pos {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+impl<T> Write for DeferredCursor<&mut T>
+where
+    T: SliceLike<Element = u8> + ?Sized,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let start = self.start();
+        let len = buf.len().min(self.len - start);
+        // SAFETY: the range lies within the buffer, so we only need to create a reference to it.
+        let bytes = unsafe { &mut *(start..start + len).get_unchecked_mut(self.inner.as_mut_ptr()) };
+        bytes.copy_from_slice(&buf[..len]);
+        self.pos += len as u64;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> From<&'a [u8]> for DeferredCursor<&'a [u8]> {
+    fn from(bytes: &'a [u8]) -> Self {
+        Self {
+            len: bytes.len(),
+            inner: Deferred::from(bytes),
+            pos: 0,
+        }
+    }
+}
+
+impl<'a> From<&'a mut [u8]> for DeferredCursor<&'a mut [u8]> {
+    fn from(bytes: &'a mut [u8]) -> Self {
+        Self {
+            len: bytes.len(),
+            inner: Deferred::from(bytes),
+            pos: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::UnsafeCell;
+    use std::io::{BufRead, ErrorKind, Read, Seek, SeekFrom, Write};
+    use crate::{DeferMut, Deferred, DeferredCursor};
+
+    #[test]
+    fn read_write_seek() {
+        let mut buffer = [0u8; 8];
+        let mut cursor = DeferredCursor::new(Deferred::from(&mut buffer));
+        cursor.write_all(b"abc").unwrap();
+        assert_eq!(3, cursor.position());
+        assert_eq!(5, cursor.remaining());
+        assert_eq!(6, cursor.seek(SeekFrom::End(-2)).unwrap());
+        assert_eq!(2, cursor.write(b"xyz").unwrap());
+        assert_eq!(0, cursor.write(b"z").unwrap());
+        assert_eq!(ErrorKind::WriteZero, cursor.write_all(b"z").unwrap_err().kind());
+        assert_eq!(ErrorKind::InvalidInput, cursor.seek(SeekFrom::Current(-9)).unwrap_err().kind());
+        assert_eq!(1, cursor.seek(SeekFrom::Current(-7)).unwrap());
+        let mut read = [0u8; 4];
+        cursor.read_exact(&mut read).unwrap();
+        assert_eq!(b"bc\0\0", &read);
+        cursor.set_position(100);
+        assert_eq!(0, cursor.read(&mut read).unwrap());
+        assert_eq!(0, cursor.write(b"a").unwrap());
+        assert_eq!(8, cursor.len());
+        assert_eq!(b"abc\0\0\0xy", &buffer);
+    }
+
+    #[test]
+    fn buf_read() {
+        let buffer = *b"first\nsecond\n";
+        let mut cursor = DeferredCursor::new(Deferred::new(&buffer));
+        let mut line = alloc::string::String::new();
+        cursor.read_line(&mut line).unwrap();
+        assert_eq!("first\n", line);
+        assert_eq!(b"second\n", cursor.fill_buf().unwrap());
+        cursor.consume(7);
+        assert!(cursor.fill_buf().unwrap().is_empty());
+    }
+
+    #[test]
+    fn from_slice() {
+        let mut buffer = [0u8; 6];
+        let mut cursor = DeferredCursor::from(&mut buffer[2..]);
+        cursor.write_all(b"abcd").unwrap();
+        assert_eq!(0, cursor.remaining());
+        let mut cursor = DeferredCursor::from(&buffer[..]);
+        let mut read = [0u8; 6];
+        cursor.read_exact(&mut read).unwrap();
+        assert_eq!(b"\0\0abcd", &read);
+    }
+
+    #[test]
+    fn disjoint_regions() {
+        let buffer = UnsafeCell::new([0u8; 8]);
+        let deferred = unsafe { buffer.defer_mut() };
+        let mut other = unsafe { deferred.clone_unchecked() };
+        let mut cursor = DeferredCursor::new(deferred);
+        cursor.set_position(4);
+        // the bytes that are written by the cursor do not overlap with the reference to the first byte
+        let first = &mut other[0];
+        cursor.write_all(&[1, 2, 3, 4]).unwrap();
+        *first = 9;
+        assert_eq!([9, 0, 0, 0, 1, 2, 3, 4], unsafe { *buffer.get() });
+    }
+}
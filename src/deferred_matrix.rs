@@ -0,0 +1,474 @@
+//! This module contains the [DeferredMatrix] view on a two-dimensional region of a deferred array or slice.
+
+use core::marker::PhantomData;
+use core::ops::{Index, IndexMut};
+use core::ptr::NonNull;
+
+use crate::{Deferred, DeferredStrided, Reference, SliceLike};
+
+/// A deferred view on a two-dimensional matrix which is stored in row-major order in an array or slice.
+/// Just like [Deferred], this comes in two flavors: `DeferredMatrix<&T>` for immutable access and
+/// `DeferredMatrix<&mut T>` for mutable access, where `T` is the type of the elements.
+///
+/// A matrix consists of `rows` rows of `cols` elements each. Consecutive rows start `row_stride` elements
+/// apart, which allows sub-matrices to skip over the elements of the other columns. A matrix can be split into
+/// disjoint sub-matrices with [DeferredMatrix::split_rows_at], [DeferredMatrix::split_cols_at] and
+/// [DeferredMatrix::tiles]. Like [Deferred], none of these create references to the elements, so the
+/// sub-matrices can be mutated at the same time, e.g. by different threads.
+///
+/// Matrices are obtained with [Deferred::matrix] and [Deferred::matrix_mut].
+///
+/// # Example
+/// ```
+/// use deferred_reference::Deferred;
+/// let mut image = [0u8; 64 * 48];
+/// let mut deferred = Deferred::from(&mut image);
+/// std::thread::scope(|scope| {
+///     for (i, mut tile) in deferred.matrix_mut(48, 64).tiles(16, 16).enumerate() {
+///         scope.spawn(move || {
+///             for row in 0..tile.rows() {
+///                 tile.row_mut(row).fill(i as u8);
+///             }
+///         });
+///     }
+/// });
+/// assert_eq!(0, image[15]);
+/// assert_eq!(1, image[16]);
+/// assert_eq!(4, image[16 * 64]);
+/// ```
+pub struct DeferredMatrix<T>
+where
+    T: Reference,
+    T::Target: Sized,
+{
+    /// A pointer to the first element of the matrix.
+    ptr: NonNull<T::Target>,
+    /// The number of rows of the matrix.
+    rows: usize,
+    /// The number of columns of the matrix.
+    cols: usize,
+    /// The distance between the first elements of two consecutive rows, measured in elements.
+    row_stride: usize,
+    /// Inherits the variance, auto traits and lifetime from the reference type.
+    _marker: PhantomData<T>,
+}
+
+// SAFETY: this is safe, because we merely inherit the Sync trait bounds from the Rust reference types.
+unsafe impl<T: Sync + Reference> Sync for DeferredMatrix<T> where T::Target: Sized {}
+
+// SAFETY: this is safe, because we merely inherit the Send trait bounds from the Rust reference types.
+unsafe impl<T: Send + Reference> Send for DeferredMatrix<T> where T::Target: Sized {}
+
+impl<T> Copy for DeferredMatrix<&T> {}
+impl<T> Clone for DeferredMatrix<&T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+/// Checks that a matrix with `rows` rows and `cols` columns fits in an array or slice of length `len`.
+///
+/// # Panics
+/// Panics if the matrix does not fit.
+#[track_caller]
+fn check_matrix_len(len: usize, rows: usize, cols: usize) {
+    match rows.checked_mul(cols) {
+        Some(size) if size <= len => {},
+        _ => panic!("matrix of {} rows and {} columns out of range for slice pointer of length {}", rows, cols, len),
+    }
+}
+
+/// # Methods for creating matrix views on deferred references to slices and arrays
+impl<T> Deferred<T>
+where
+    T: Reference,
+    T::Target: SliceLike,
+{
+    /// Returns a view on the first `rows * cols` elements of the array or slice as a matrix
+    /// with `rows` rows and `cols` columns in row-major order.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let buffer = [1, 2, 3, 4, 5, 6];
+    /// let deferred = Deferred::new(&buffer);
+    /// let matrix = deferred.matrix(2, 3);
+    /// assert_eq!(6, matrix[(1, 2)]);
+    /// assert_eq!([4, 5, 6], *matrix.row(1));
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if `rows * cols` exceeds the length of the array or slice.
+    /// This method also needs the length of the slice. See [Deferred::len] for when this can panic.
+    #[track_caller]
+    pub fn matrix(&self, rows: usize, cols: usize) -> DeferredMatrix<&<T::Target as SliceLike>::Element> {
+        check_matrix_len(self.len(), rows, cols);
+        DeferredMatrix {
+            // SAFETY: the pointer is derived from a non-null pointer.
+            ptr: unsafe { NonNull::new_unchecked(self.as_ptr() as *mut _) },
+            rows,
+            cols,
+            row_stride: cols,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// # Methods for creating matrix views on deferred _mutable_ references to slices and arrays
+impl<T> Deferred<&mut T>
+where
+    T: SliceLike + ?Sized,
+{
+    /// Returns a mutable view on the first `rows * cols` elements of the array or slice as a matrix
+    /// with `rows` rows and `cols` columns in row-major order.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let mut buffer = [0; 6];
+    /// let mut deferred = Deferred::from(&mut buffer);
+    /// let mut matrix = deferred.matrix_mut(2, 3);
+    /// matrix[(1, 2)] = 6;
+    /// assert_eq!([0, 0, 0, 0, 0, 6], buffer);
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if `rows * cols` exceeds the length of the array or slice.
+    /// This method also needs the length of the slice. See [Deferred::len] for when this can panic.
+    #[track_caller]
+    pub fn matrix_mut(&mut self, rows: usize, cols: usize) -> DeferredMatrix<&mut T::Element> {
+        check_matrix_len(self.len(), rows, cols);
+        DeferredMatrix {
+            // SAFETY: the pointer is derived from a non-null pointer.
+            ptr: unsafe { NonNull::new_unchecked(self.as_mut_ptr() as *mut T::Element) },
+            rows,
+            cols,
+            row_stride: cols,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> DeferredMatrix<T>
+where
+    T: Reference,
+    T::Target: Sized,
+{
+    /// Returns the number of rows of the matrix.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the number of columns of the matrix.
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Returns the distance between the first elements of two consecutive rows, measured in elements.
+    /// This is equal to the number of columns of the matrix that this matrix was split from.
+    pub fn row_stride(&self) -> usize {
+        self.row_stride
+    }
+
+    /// Returns `true` if the matrix contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.rows == 0 || self.cols == 0
+    }
+
+    /// Returns a pointer to the element at `row` and `col`, or the pointer to the first element
+    /// of the matrix if the sub-matrix starting at `row` and `col` would be empty.
+    ///
+    /// # Safety
+    /// `row` must not exceed `self.rows()` and `col` must not exceed `self.cols()`.
+    unsafe fn ptr_at(&self, row: usize, col: usize) -> NonNull<T::Target> {
+        if row == self.rows || col == self.cols {
+            // this avoids pointer arithmetic beyond the end of the array or slice
+            self.ptr
+        } else {
+            NonNull::new_unchecked(self.ptr.as_ptr().add(row * self.row_stride + col))
+        }
+    }
+
+    /// Returns the sub-matrix with `rows` rows and `cols` columns starting at `row` and `col`.
+    ///
+    /// # Safety
+    /// The sub-matrix must lie within this matrix.
+    unsafe fn sub_matrix(&self, row: usize, col: usize, rows: usize, cols: usize) -> Self {
+        DeferredMatrix {
+            ptr: self.ptr_at(row, col),
+            rows,
+            cols,
+            row_stride: self.row_stride,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns a reference to the element at `row` and `col`, or `None` if either is out of bounds.
+    /// No references to the other elements are created.
+    pub fn get(&self, row: usize, col: usize) -> Option<&T::Target> {
+        if row < self.rows && col < self.cols {
+            // SAFETY: `row` and `col` are in bounds.
+            Some(unsafe { &*self.ptr_at(row, col).as_ptr() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a deferred immutable reference to the row at index `row`.
+    ///
+    /// # Panics
+    /// Panics if `row` is out of bounds.
+    #[track_caller]
+    pub fn row(&self, row: usize) -> Deferred<&[T::Target]> {
+        assert!(row < self.rows, "row {} out of range for matrix with {} rows", row, self.rows);
+        // SAFETY: `row` is in bounds and the row lies within the matrix.
+        unsafe { Deferred::from_raw_parts(self.ptr_at(row, 0).as_ptr(), self.cols) }
+    }
+
+    /// Returns a strided view on the column at index `col`.
+    ///
+    /// # Panics
+    /// Panics if `col` is out of bounds.
+    #[track_caller]
+    pub fn col(&self, col: usize) -> DeferredStrided<&T::Target> {
+        assert!(col < self.cols, "column {} out of range for matrix with {} columns", col, self.cols);
+        // SAFETY: `col` is in bounds and the column lies within the matrix.
+        unsafe { DeferredStrided::from_raw_parts(self.ptr_at(0, col), self.rows, self.row_stride) }
+    }
+
+    /// Splits the matrix into the rows above index `mid` and the rows starting at index `mid`.
+    /// This consumes the matrix, so that mutable sub-matrices never overlap.
+    ///
+    /// # Panics
+    /// Panics if `mid` exceeds the number of rows.
+    #[track_caller]
+    pub fn split_rows_at(self, mid: usize) -> (Self, Self) {
+        assert!(mid <= self.rows, "row {} out of range for matrix with {} rows", mid, self.rows);
+        // SAFETY: both sub-matrices lie within this matrix and they do not overlap.
+        unsafe { (self.sub_matrix(0, 0, mid, self.cols), self.sub_matrix(mid, 0, self.rows - mid, self.cols)) }
+    }
+
+    /// Splits the matrix into the columns left of index `mid` and the columns starting at index `mid`.
+    /// This consumes the matrix, so that mutable sub-matrices never overlap.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let mut buffer = [0; 6];
+    /// let mut deferred = Deferred::from(&mut buffer);
+    /// let (mut left, mut right) = deferred.matrix_mut(2, 3).split_cols_at(1);
+    /// // dereferencing the row before indexing makes this work without the `slice_ptr_len` feature, too
+    /// (*left.row_mut(1))[0] = 1;
+    /// right.row_mut(1).copy_from_slice(&[2, 3]);
+    /// assert_eq!([0, 0, 0, 1, 2, 3], buffer);
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if `mid` exceeds the number of columns.
+    #[track_caller]
+    pub fn split_cols_at(self, mid: usize) -> (Self, Self) {
+        assert!(mid <= self.cols, "column {} out of range for matrix with {} columns", mid, self.cols);
+        // SAFETY: both sub-matrices lie within this matrix and they do not overlap.
+        unsafe { (self.sub_matrix(0, 0, self.rows, mid), self.sub_matrix(0, mid, self.rows, self.cols - mid)) }
+    }
+
+    /// Returns an iterator over disjoint sub-matrices of (at most) `height` rows and `width` columns,
+    /// in row-major order. The tiles at the bottom and right edges are smaller if the number of rows
+    /// or columns is not a multiple of `height` or `width`, respectively.
+    /// This consumes the matrix, so that mutable tiles never overlap.
+    ///
+    /// # Panics
+    /// Panics if `height` or `width` is zero.
+    #[track_caller]
+    #[allow(clippy::manual_is_multiple_of)] // `usize::is_multiple_of` requires Rust 1.87
+    pub fn tiles(self, height: usize, width: usize) -> impl DoubleEndedIterator<Item = Self> + ExactSizeIterator {
+        assert!(height != 0 && width != 0, "tile size must be non-zero");
+        let tile_rows = self.rows / height + (self.rows % height != 0) as usize;
+        let tile_cols = self.cols / width + (self.cols % width != 0) as usize;
+        (0..tile_rows * tile_cols).map(move |index| {
+            let (row, col) = (index / tile_cols * height, index % tile_cols * width);
+            // SAFETY: the tiles lie within this matrix and they do not overlap.
+            unsafe { self.sub_matrix(row, col, height.min(self.rows - row), width.min(self.cols - col)) }
+        })
+    }
+}
+
+impl<T> DeferredMatrix<&mut T> {
+    /// Returns a mutable reference to the element at `row` and `col`, or `None` if either is out of bounds.
+    /// No references to the other elements are created.
+    pub fn get_mut(&mut self, row: usize, col: usize) -> Option<&mut T> {
+        if row < self.rows && col < self.cols {
+            // SAFETY: `row` and `col` are in bounds.
+            Some(unsafe { &mut *self.ptr_at(row, col).as_ptr() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a deferred mutable reference to the row at index `row`.
+    ///
+    /// # Panics
+    /// Panics if `row` is out of bounds.
+    #[track_caller]
+    pub fn row_mut(&mut self, row: usize) -> Deferred<&mut [T]> {
+        assert!(row < self.rows, "row {} out of range for matrix with {} rows", row, self.rows);
+        // SAFETY: `row` is in bounds and the row lies within the matrix.
+        unsafe { Deferred::from_raw_parts_mut(self.ptr_at(row, 0).as_ptr(), self.cols) }
+    }
+
+    /// Returns a mutable strided view on the column at index `col`.
+    ///
+    /// # Panics
+    /// Panics if `col` is out of bounds.
+    #[track_caller]
+    pub fn col_mut(&mut self, col: usize) -> DeferredStrided<&mut T> {
+        assert!(col < self.cols, "column {} out of range for matrix with {} columns", col, self.cols);
+        // SAFETY: `col` is in bounds and the column lies within the matrix.
+        unsafe { DeferredStrided::from_raw_parts(self.ptr_at(0, col), self.rows, self.row_stride) }
+    }
+}
+
+impl<'a, T> DeferredMatrix<&'a mut T> {
+    /// Converts this mutable matrix into an immutable matrix.
+    pub fn into_ref(self) -> DeferredMatrix<&'a T> {
+        DeferredMatrix {
+            ptr: self.ptr,
+            rows: self.rows,
+            cols: self.cols,
+            row_stride: self.row_stride,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Index<(usize, usize)> for DeferredMatrix<T>
+where
+    T: Reference,
+    T::Target: Sized,
+{
+    type Output = T::Target;
+
+    #[track_caller]
+    fn index(&self, (row, col): (usize, usize)) -> &Self::Output {
+        match self.get(row, col) {
+            Some(element) => element,
+            None => panic!("index ({}, {}) out of range for matrix of {} rows and {} columns", row, col, self.rows, self.cols),
+        }
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for DeferredMatrix<&mut T> {
+    #[track_caller]
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut Self::Output {
+        let (rows, cols) = (self.rows, self.cols);
+        match self.get_mut(row, col) {
+            Some(element) => element,
+            None => panic!("index ({}, {}) out of range for matrix of {} rows and {} columns", row, col, rows, cols),
+        }
+    }
+}
+
+impl<T> core::fmt::Debug for DeferredMatrix<T>
+where
+    T: Reference,
+    T::Target: Sized + core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries((0..self.rows).map(|row| self.row(row))).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::cell::UnsafeCell;
+    use crate::{DeferMut, Deferred};
+
+    #[test]
+    fn split() {
+        let buffer = UnsafeCell::new([0u32; 20]);
+        let mut deferred = unsafe { buffer.defer_mut() };
+        let matrix = deferred.matrix_mut(4, 5);
+        let (top, bottom) = matrix.split_rows_at(1);
+        let (mut left, mut right) = bottom.split_cols_at(2);
+        assert_eq!((1, 5, 5), (top.rows(), top.cols(), top.row_stride()));
+        assert_eq!((3, 2, 5), (left.rows(), left.cols(), left.row_stride()));
+        assert_eq!((3, 3, 5), (right.rows(), right.cols(), right.row_stride()));
+        // all three sub-matrices are alive and mutated at the same time
+        let (a, b) = (left.get_mut(2, 1).unwrap(), right.get_mut(0, 2).unwrap());
+        *a = 1;
+        *b = 2;
+        right[(2, 0)] = 3;
+        assert_eq!(None, left.get(3, 0));
+        assert_eq!(None, right.get(0, 3));
+        let values = unsafe { *buffer.get() };
+        assert_eq!(1, values[3 * 5 + 1]);
+        assert_eq!(2, values[5 + 4]);
+        assert_eq!(3, values[3 * 5 + 2]);
+        assert_eq!(
+            "[[0, 0, 0, 0, 2], [0, 0, 0, 0, 0], [0, 1, 3, 0, 0]]",
+            alloc::format!("{:?}", deferred.matrix(4, 5).split_rows_at(1).1)
+        );
+    }
+
+    #[test]
+    fn empty_splits() {
+        let buffer = [0u8; 6];
+        let deferred = Deferred::new(&buffer);
+        let matrix = deferred.matrix(2, 3);
+        let (top, bottom) = matrix.split_rows_at(2);
+        assert!(bottom.is_empty() && !top.is_empty());
+        let (left, right) = matrix.split_cols_at(0);
+        assert!(left.is_empty() && !right.is_empty());
+        assert_eq!(0, right.split_cols_at(3).1.tiles(1, 1).len());
+    }
+
+    #[test]
+    fn tiles() {
+        let buffer = UnsafeCell::new([0usize; 7 * 5]);
+        let mut deferred = unsafe { buffer.defer_mut() };
+        let tiles: Vec<_> = deferred.matrix_mut(7, 5).tiles(3, 2).collect();
+        assert_eq!(9, tiles.len());
+        let sizes: Vec<_> = tiles.iter().map(|tile| (tile.rows(), tile.cols())).collect();
+        assert_eq!([(3, 2), (3, 2), (3, 1), (3, 2), (3, 2), (3, 1), (1, 2), (1, 2), (1, 1)], sizes[..]);
+        for (i, mut tile) in tiles.into_iter().enumerate() {
+            for row in 0..tile.rows() {
+                for col in 0..tile.cols() {
+                    tile[(row, col)] = i;
+                }
+            }
+        }
+        let matrix = deferred.matrix(7, 5);
+        assert_eq!([0, 0, 1, 1, 2], *matrix.row(0));
+        assert_eq!([6, 6, 7, 7, 8], *matrix.row(6));
+        let col: Vec<_> = matrix.col(4).iter().copied().collect();
+        assert_eq!([2, 2, 2, 5, 5, 5, 8], col[..]);
+    }
+
+    #[test]
+    fn rows_and_cols() {
+        let mut buffer = [0i32; 12];
+        let mut deferred = Deferred::from(&mut buffer);
+        let (_, mut right) = deferred.matrix_mut(3, 4).split_cols_at(1);
+        for (i, element) in right.col_mut(1).iter_mut().enumerate() {
+            *element = i as i32 + 1;
+        }
+        right.row_mut(0).copy_from_slice(&[-1, -2, -3]);
+        assert_eq!(-2, right.into_ref()[(0, 1)]);
+        assert_eq!([0, -1, -2, -3, 0, 0, 2, 0, 0, 0, 3, 0], buffer);
+    }
+
+    #[test]
+    #[should_panic]
+    fn too_large() {
+        let buffer = [0u8; 6];
+        Deferred::new(&buffer).matrix(3, 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn row_out_of_bounds() {
+        let buffer = [0u8; 6];
+        Deferred::new(&buffer).matrix(2, 3).row(2);
+    }
+}
@@ -0,0 +1,194 @@
+//! This module contains the [DeferredMmap] struct, which exposes memory-mapped files and anonymous mappings as deferred byte slices.
+
+use core::convert::TryFrom;
+use core::ptr::NonNull;
+use std::fs::File;
+use std::io;
+use std::os::unix::io::AsRawFd;
+
+use crate::Deferred;
+
+/// A memory mapping of a file or of anonymous memory, which is exposed as a deferred byte slice
+/// (i.e. `Deferred<&[u8]>` and `Deferred<&mut [u8]>`) that borrows the mapping. The mapping is unmapped
+/// when the [DeferredMmap] is dropped, which is only possible once all of the deferred references are out of use.
+///
+/// The mapping can be split into disjoint segments with [DeferredMmap::segments_mut], which can be written to
+/// from different threads at the same time. This requires the `mmap` feature and is only available on Linux.
+///
+/// # Example
+/// ```
+/// use deferred_reference::DeferredMmap;
+/// let mut mmap = DeferredMmap::anonymous(4096).unwrap();
+/// std::thread::scope(|scope| {
+///     for (index, mut segment) in mmap.segments_mut(1024).enumerate() {
+///         scope.spawn(move || segment.fill(index as u8));
+///     }
+/// });
+/// let bytes = &*mmap.as_deferred();
+/// assert_eq!([0, 1, 2, 3], [bytes[0], bytes[1024], bytes[2048], bytes[3072]]);
+/// ```
+pub struct DeferredMmap {
+    /// The start of the mapping. This is dangling if the mapping is empty.
+    ptr: NonNull<u8>,
+    /// The length of the mapping in bytes.
+    len: usize,
+}
+
+// SAFETY: the mapping is owned by `DeferredMmap` just like a `Vec<u8>` owns its buffer.
+unsafe impl Send for DeferredMmap {}
+
+// SAFETY: the mapping can only be mutated through a mutable reference to `DeferredMmap`.
+unsafe impl Sync for DeferredMmap {}
+
+impl DeferredMmap {
+    /// Maps `len` bytes with the given flags, optionally backed by the file descriptor `fd`.
+    fn map_raw(len: usize, flags: libc::c_int, fd: libc::c_int) -> io::Result<Self> {
+        if len == 0 {
+            // mapping zero bytes is an error, so empty mappings are not mapped at all
+            return Ok(Self { ptr: NonNull::dangling(), len });
+        }
+        // SAFETY: a new mapping is created at an address chosen by the kernel, so this does not affect any existing memory.
+        let ptr = unsafe {
+            libc::mmap(core::ptr::null_mut(), len, libc::PROT_READ | libc::PROT_WRITE, flags, fd, 0)
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            // SAFETY: a successful mapping is never located at the null address.
+            ptr: unsafe { NonNull::new_unchecked(ptr as *mut u8) },
+            len,
+        })
+    }
+
+    /// Maps the whole contents of `file` into memory. Writes to the mapping are written back to the file.
+    /// The file must be opened for both reading and writing.
+    ///
+    /// # Errors
+    /// Returns an error if the length of the file does not fit in a `usize` or if the file can not be mapped.
+    ///
+    /// # Safety
+    /// The contents of a mapped file can be changed by other processes at any time and accessing the mapping
+    /// after the file has been truncated raises a `SIGBUS` signal. The caller must ensure that the file is
+    /// neither modified nor truncated by anything else than this mapping for as long as the mapping exists.
+    pub unsafe fn map(file: &File) -> io::Result<Self> {
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "file is too large to be mapped"))?;
+        Self::map_raw(len, libc::MAP_SHARED, file.as_raw_fd())
+    }
+
+    /// Creates a private anonymous mapping of `len` bytes, which are initialized to zero.
+    ///
+    /// # Errors
+    /// Returns an error if the memory can not be mapped.
+    pub fn anonymous(len: usize) -> io::Result<Self> {
+        Self::map_raw(len, libc::MAP_PRIVATE | libc::MAP_ANONYMOUS, -1)
+    }
+
+    /// Returns the length of the mapping in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the mapping has a length of 0 bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns a deferred immutable reference to the mapping.
+    pub fn as_deferred(&self) -> Deferred<&[u8]> {
+        // SAFETY: the mapping is valid for `self.len` bytes for as long as `self` is borrowed.
+        unsafe { Deferred::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Returns a deferred mutable reference to the mapping.
+    pub fn as_deferred_mut(&mut self) -> Deferred<&mut [u8]> {
+        // SAFETY: the mapping is valid for `self.len` bytes for as long as `self` is mutably borrowed.
+        unsafe { Deferred::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Splits the mapping into disjoint segments of `segment_len` bytes, which can be written to at the same time.
+    /// The last segment is shorter if the length of the mapping is not a multiple of `segment_len`.
+    ///
+    /// # Panics
+    /// Panics if `segment_len` is 0.
+    pub fn segments_mut(&mut self, segment_len: usize) -> impl DoubleEndedIterator<Item = Deferred<&mut [u8]>> + ExactSizeIterator + '_ {
+        assert!(segment_len != 0, "segment length must be non-zero");
+        let (ptr, len) = (self.ptr.as_ptr(), self.len);
+        (0..len / segment_len + (len % segment_len != 0) as usize).map(move |index| {
+            let start = index * segment_len;
+            // SAFETY: the segments are disjoint and they all lie within the mapping, which is mutably borrowed.
+            unsafe { Deferred::from_raw_parts_mut(ptr.add(start), segment_len.min(len - start)) }
+        })
+    }
+
+    /// Synchronously writes the modified contents of a file mapping back to the file.
+    /// This has no effect on anonymous mappings.
+    ///
+    /// # Errors
+    /// Returns an error if the contents can not be written back.
+    pub fn flush(&self) -> io::Result<()> {
+        if self.len == 0 {
+            return Ok(());
+        }
+        // SAFETY: the mapping is valid for `self.len` bytes.
+        if unsafe { libc::msync(self.ptr.as_ptr() as *mut libc::c_void, self.len, libc::MS_SYNC) } == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+impl Drop for DeferredMmap {
+    fn drop(&mut self) {
+        if self.len != 0 {
+            // SAFETY: all deferred references borrow `self`, so the mapping is no longer in use.
+            unsafe { libc::munmap(self.ptr.as_ptr() as *mut libc::c_void, self.len) };
+        }
+    }
+}
+
+impl core::fmt::Debug for DeferredMmap {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("DeferredMmap").field("ptr", &self.ptr).field("len", &self.len).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use crate::DeferredMmap;
+
+    #[test]
+    fn file() {
+        let path = std::env::temp_dir().join(format!("deferred-reference-mmap-{}", std::process::id()));
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path).unwrap();
+        file.write_all(&[0; 10]).unwrap();
+        {
+            let mut mmap = unsafe { DeferredMmap::map(&file) }.unwrap();
+            assert_eq!(10, mmap.len());
+            {
+                let mut segments = mmap.segments_mut(4);
+                assert_eq!(3, segments.len());
+                let mut last = segments.next_back().unwrap();
+                let mut first = segments.next().unwrap();
+                last.copy_from_slice(b"yz");
+                first.copy_from_slice(b"abcd");
+            }
+            mmap.flush().unwrap();
+        }
+        assert_eq!(b"abcd\0\0\0\0yz", &std::fs::read(&path).unwrap()[..]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn empty() {
+        let mut mmap = DeferredMmap::anonymous(0).unwrap();
+        assert!(mmap.is_empty());
+        assert_eq!(0, mmap.segments_mut(16).len());
+        assert!(mmap.as_deferred_mut().is_empty());
+        mmap.flush().unwrap();
+    }
+}
@@ -0,0 +1,418 @@
+//! This module contains the [DeferredOutput] struct, which initializes a deferred uninitialized slice from multiple workers.
+
+use core::cmp::Ordering;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ops::Range;
+use core::ptr::NonNull;
+use std::sync::{Mutex, MutexGuard};
+use std::vec::Vec;
+
+use crate::{Deferred, PointerLength};
+
+/// Records which index ranges of a [DeferredOutput] have been initialized by each of its writers.
+/// The ranges are recorded by the [OutputWriter]s when they are dropped, so the tracker can only be read by callers.
+#[derive(Debug, Default)]
+pub struct InitTracker {
+    /// The sorted and disjoint ranges of initialized elements, one per writer.
+    ranges: Mutex<Vec<Range<usize>>>,
+}
+
+impl InitTracker {
+    /// Locks the ranges. A worker which panics never holds the lock, so poisoning is ignored.
+    fn lock(&self) -> MutexGuard<'_, Vec<Range<usize>>> {
+        self.ranges.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    /// Records that a writer has initialized the elements in `range`.
+    #[allow(clippy::unnecessary_map_or)] // `Option::is_none_or` requires Rust 1.82
+    fn record(&self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        let mut ranges = self.lock();
+        // note: the comparison never returns `Equal`, so this always returns the insertion point as an `Err`
+        let index = ranges
+            .binary_search_by(|other| if other.end <= range.start { Ordering::Less } else { Ordering::Greater })
+            .unwrap_or_else(|index| index);
+        debug_assert!(ranges.get(index).map_or(true, |next| range.end <= next.start), "ranges must be disjoint");
+        ranges.insert(index, range);
+    }
+
+    /// Returns the number of initialized elements.
+    pub fn initialized(&self) -> usize {
+        self.lock().iter().map(|range| range.len()).sum()
+    }
+
+    /// Returns the index of the first element that has not been initialized, or `None` if there is no gap
+    /// between the recorded ranges before `len`.
+    fn first_missing(&self, len: usize) -> Option<usize> {
+        let mut next = 0;
+        for range in self.lock().iter() {
+            if range.start != next {
+                break;
+            }
+            next = range.end;
+        }
+        if next < len {
+            Some(next)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the sorted ranges of initialized elements, with one range per writer which initialized
+    /// at least one element. Adjacent ranges of different writers are not merged, so a gap between two
+    /// ranges shows which writer did not initialize (all of) its chunk.
+    pub fn ranges(&self) -> Vec<Range<usize>> {
+        self.lock().clone()
+    }
+}
+
+/// The error returned by [DeferredOutput::finish] when not all elements of the output have been initialized.
+/// The initialized elements have already been dropped when this error is returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IncompleteOutput {
+    /// The number of elements that were initialized.
+    pub initialized: usize,
+    /// The number of elements in the output.
+    pub len: usize,
+    /// The index of the first element that was not initialized. This lies in the chunk of
+    /// the first writer which did not initialize all of its elements.
+    pub first_missing: usize,
+}
+
+impl core::fmt::Display for IncompleteOutput {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "only {} of {} output elements were initialized, starting with a gap at index {}",
+            self.initialized, self.len, self.first_missing,
+        )
+    }
+}
+
+impl std::error::Error for IncompleteOutput {}
+
+/// An uninitialized output buffer which is initialized in parallel by multiple [OutputWriter]s, which write
+/// to disjoint parts of the buffer. An [InitTracker] records which elements have been initialized by each of the
+/// writers, so that [DeferredOutput::finish] can check that the whole buffer has been initialized. If the output
+/// is dropped without being finished (e.g. because one of the workers panicked), then only the initialized elements
+/// are dropped. This requires the `std` feature.
+///
+/// # Example
+/// ```
+/// use deferred_reference::DeferredOutput;
+/// let mut squares = Vec::new();
+/// DeferredOutput::collect_into(&mut squares, 1000, |output| {
+///     std::thread::scope(|scope| {
+///         for mut writer in output.writers(250) {
+///             scope.spawn(move || {
+///                 let range = writer.range();
+///                 writer.extend(range.map(|i| i * i));
+///             });
+///         }
+///     });
+/// }).unwrap();
+/// assert_eq!(999 * 999, squares[999]);
+/// ```
+pub struct DeferredOutput<'a, T> {
+    /// The start of the output buffer.
+    ptr: NonNull<T>,
+    /// The number of elements in the output buffer.
+    len: usize,
+    /// Whether the writers have been handed out already.
+    split: bool,
+    /// The ranges of initialized elements.
+    tracker: InitTracker,
+    /// The output buffer is mutably borrowed for `'a`.
+    _marker: PhantomData<&'a mut [MaybeUninit<T>]>,
+}
+
+// SAFETY: the output owns the initialized elements, just like a `Vec<T>`.
+unsafe impl<T: Send> Send for DeferredOutput<'_, T> {}
+
+// SAFETY: the output does not give shared access to any of its elements.
+unsafe impl<T: Send> Sync for DeferredOutput<'_, T> {}
+
+impl<'a, T> DeferredOutput<'a, T> {
+    /// Creates a new output over the uninitialized array or slice that `memory` points to.
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    /// Use the [From] implementation on `&mut [MaybeUninit<T>]` to construct an output on stable Rust.
+    pub fn new(memory: Deferred<&'a mut [MaybeUninit<T>]>) -> Self {
+        let len = PointerLength::len(memory.as_ptr());
+        Self::from_parts(memory, len)
+    }
+
+    /// Creates a new output over the uninitialized slice that `memory` points to, which has `len` elements.
+    fn from_parts(memory: Deferred<&'a mut [MaybeUninit<T>]>, len: usize) -> Self {
+        Self {
+            // SAFETY: the pointer originates from a deferred reference, so it is not null.
+            ptr: unsafe { NonNull::new_unchecked(memory.as_mut_ptr() as *mut T) },
+            len,
+            split: false,
+            tracker: InitTracker::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Appends `len` elements to `vec`, which are initialized by the writers of the output that is passed to `fill`.
+    /// The elements are only appended if `fill` initialized all of them, otherwise the initialized elements are dropped.
+    /// If `fill` panics, then the initialized elements are dropped as well and `vec` is left unchanged.
+    ///
+    /// # Errors
+    /// Returns an error if `fill` did not initialize all `len` elements.
+    pub fn collect_into<F>(vec: &mut Vec<T>, len: usize, fill: F) -> Result<(), IncompleteOutput>
+    where
+        F: FnOnce(&mut DeferredOutput<'_, T>),
+    {
+        vec.reserve(len);
+        // SAFETY: `reserve` made room for at least `len` more elements after the old length of `vec`,
+        // SAFETY: and `MaybeUninit<T>` does not need to be initialized.
+        let spare = unsafe { core::slice::from_raw_parts_mut(vec.as_mut_ptr().add(vec.len()) as *mut MaybeUninit<T>, len) };
+        let mut output = DeferredOutput::from(spare);
+        fill(&mut output);
+        output.finish()?;
+        // SAFETY: the `len` elements after the old length of `vec` have all been initialized.
+        unsafe { vec.set_len(vec.len() + len) };
+        Ok(())
+    }
+
+    /// Returns the number of elements in the output.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the output has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the tracker which records which elements have been initialized.
+    pub fn tracker(&self) -> &InitTracker {
+        &self.tracker
+    }
+
+    /// Splits the output into writers for disjoint chunks of `chunk_len` elements, which can initialize
+    /// their chunks at the same time. The last chunk is shorter if the length of the output is not
+    /// a multiple of `chunk_len`.
+    ///
+    /// # Panics
+    /// Panics if `chunk_len` is 0 or if the writers have already been handed out before.
+    pub fn writers(&mut self, chunk_len: usize) -> impl DoubleEndedIterator<Item = OutputWriter<'_, T>> + ExactSizeIterator {
+        assert!(chunk_len != 0, "chunk length must be non-zero");
+        assert!(!self.split, "the writers of a DeferredOutput can only be handed out once");
+        self.split = true;
+        let (ptr, len, tracker) = (self.ptr, self.len, &self.tracker);
+        (0..len / chunk_len + (len % chunk_len != 0) as usize).map(move |index| {
+            let start = index * chunk_len;
+            OutputWriter {
+                // SAFETY: the chunk lies within the output.
+                ptr: unsafe { NonNull::new_unchecked(ptr.as_ptr().add(start)) },
+                start,
+                capacity: chunk_len.min(len - start),
+                len: 0,
+                tracker,
+                _marker: PhantomData,
+            }
+        })
+    }
+
+    /// Checks that all elements of the output have been initialized and returns a deferred mutable reference to them.
+    ///
+    /// # Errors
+    /// Returns an error if not all elements have been initialized. In that case the initialized elements are dropped.
+    pub fn finish(self) -> Result<Deferred<&'a mut [T]>, IncompleteOutput> {
+        if let Some(first_missing) = self.tracker.first_missing(self.len) {
+            let initialized = self.tracker.initialized();
+            return Err(IncompleteOutput { initialized, len: self.len, first_missing });
+        }
+        // the elements are now owned by the caller, so they must not be dropped by the output anymore
+        self.tracker.lock().clear();
+        // SAFETY: all elements of the output have been initialized.
+        Ok(unsafe { Deferred::from_raw_parts_mut(self.ptr.as_ptr(), self.len) })
+    }
+}
+
+impl<'a, T> From<&'a mut [MaybeUninit<T>]> for DeferredOutput<'a, T> {
+    fn from(memory: &'a mut [MaybeUninit<T>]) -> Self {
+        let len = memory.len();
+        // SAFETY: the pointer and the length both originate from the same slice.
+        Self::from_parts(unsafe { Deferred::from_raw_parts_mut(memory.as_mut_ptr(), len) }, len)
+    }
+}
+
+impl<T> Drop for DeferredOutput<'_, T> {
+    fn drop(&mut self) {
+        for range in self.tracker.lock().drain(..) {
+            // SAFETY: the elements in the range have been initialized and they are owned by the output.
+            unsafe {
+                core::ptr::drop_in_place(core::ptr::slice_from_raw_parts_mut(self.ptr.as_ptr().add(range.start), range.len()));
+            }
+        }
+    }
+}
+
+impl<T> core::fmt::Debug for DeferredOutput<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("DeferredOutput").field("len", &self.len).field("tracker", &self.tracker).finish()
+    }
+}
+
+/// A writer which initializes a chunk of a [DeferredOutput] from front to back.
+/// When the writer is dropped, the initialized part of its chunk is recorded in the [InitTracker] of the output.
+pub struct OutputWriter<'o, T> {
+    /// The start of the chunk.
+    ptr: NonNull<T>,
+    /// The index of the first element of the chunk in the output.
+    start: usize,
+    /// The number of elements in the chunk.
+    capacity: usize,
+    /// The number of initialized elements at the front of the chunk.
+    len: usize,
+    /// The tracker of the output.
+    tracker: &'o InitTracker,
+    /// The chunk is mutably borrowed for `'o`.
+    _marker: PhantomData<&'o mut [MaybeUninit<T>]>,
+}
+
+// SAFETY: the writer has exclusive access to its chunk, just like a `&mut [T]`.
+unsafe impl<T: Send> Send for OutputWriter<'_, T> {}
+
+// SAFETY: the writer does not give shared access to any of its elements.
+unsafe impl<T: Send> Sync for OutputWriter<'_, T> {}
+
+impl<T> OutputWriter<'_, T> {
+    /// Returns the range of indices of the chunk in the output.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.start + self.capacity
+    }
+
+    /// Returns the number of elements that have been initialized by this writer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if this writer has not initialized any elements yet.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of elements that still need to be initialized by this writer.
+    pub fn remaining(&self) -> usize {
+        self.capacity - self.len
+    }
+
+    /// Initializes the next element of the chunk with `value`.
+    ///
+    /// # Panics
+    /// Panics if all elements of the chunk have already been initialized.
+    #[track_caller]
+    pub fn push(&mut self, value: T) {
+        assert!(self.len < self.capacity, "OutputWriter::push: chunk is already full");
+        // SAFETY: the element lies within the chunk and it has not been initialized yet.
+        unsafe { self.ptr.as_ptr().add(self.len).write(value) };
+        self.len += 1;
+    }
+}
+
+impl<T> Extend<T> for OutputWriter<'_, T> {
+    /// Initializes the next elements of the chunk with the items of `iter`.
+    ///
+    /// # Panics
+    /// Panics if `iter` yields more items than [OutputWriter::remaining].
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|value| self.push(value));
+    }
+}
+
+impl<T> Drop for OutputWriter<'_, T> {
+    fn drop(&mut self) {
+        self.tracker.record(self.start..self.start + self.len);
+    }
+}
+
+impl<T> core::fmt::Debug for OutputWriter<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OutputWriter").field("range", &self.range()).field("len", &self.len).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::rc::Rc;
+    use alloc::vec::Vec;
+    use core::mem::MaybeUninit;
+    use crate::{DeferredOutput, IncompleteOutput};
+
+    #[test]
+    fn tracker_records_writers() {
+        let mut buffer = [MaybeUninit::<u8>::uninit(); 10];
+        let mut output = DeferredOutput::from(&mut buffer[..]);
+        let mut writers: Vec<_> = output.writers(3).collect();
+        writers[3].push(9);
+        writers[1].extend([3, 4]);
+        writers[0].extend([0, 1, 2]);
+        writers.swap(0, 3);
+        drop(writers);
+        assert_eq!(alloc::vec![0..3, 3..5, 9..10], output.tracker().ranges());
+        let error = IncompleteOutput { initialized: 6, len: 10, first_missing: 5 };
+        assert_eq!(Err(error), output.finish().map(|_| ()));
+    }
+
+    #[test]
+    fn finish() {
+        let mut buffer = [MaybeUninit::<u8>::uninit(); 5];
+        let mut output = DeferredOutput::from(&mut buffer[..]);
+        for mut writer in output.writers(2).rev() {
+            let range = writer.range();
+            writer.extend(range.map(|i| i as u8));
+        }
+        assert_eq!(&[0, 1, 2, 3, 4], &*output.finish().unwrap());
+    }
+
+    #[test]
+    #[allow(clippy::manual_repeat_n)] // `core::iter::repeat_n` requires Rust 1.82
+    fn drops_initialized() {
+        let rc = Rc::new(());
+        let mut vec = alloc::vec![rc.clone()];
+        let result = DeferredOutput::collect_into(&mut vec, 6, |output| {
+            for (index, mut writer) in output.writers(2).enumerate() {
+                writer.extend(core::iter::repeat(rc.clone()).take(index));
+            }
+        });
+        assert_eq!(Err(IncompleteOutput { initialized: 3, len: 6, first_missing: 0 }), result);
+        assert_eq!(2, Rc::strong_count(&rc));
+        assert_eq!(1, vec.len());
+    }
+
+    #[test]
+    fn panic_safety() {
+        extern crate std;
+        let rc = Rc::new(());
+        let mut vec = Vec::new();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            DeferredOutput::collect_into(&mut vec, 4, |output| {
+                for mut writer in output.writers(2) {
+                    writer.push(rc.clone());
+                    writer.push(rc.clone());
+                    writer.push(rc.clone());
+                }
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(1, Rc::strong_count(&rc));
+        assert!(vec.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn writers_once() {
+        let mut buffer = [MaybeUninit::<u8>::uninit(); 4];
+        let mut output = DeferredOutput::from(&mut buffer[..]);
+        output.writers(2).for_each(drop);
+        output.writers(2).for_each(drop);
+    }
+}
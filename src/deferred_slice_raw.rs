@@ -0,0 +1,265 @@
+//! This module contains the `#[repr(C)]` types for passing deferred slices across FFI boundaries.
+
+use crate::Deferred;
+
+/// A C-compatible representation of a deferred immutable reference to a slice (i.e. a `Deferred<&[T]>`).
+///
+/// [Deferred] is `#[repr(transparent)]`, so a deferred reference to a sized type can be passed to foreign
+/// code as a regular pointer. Deferred slices however are fat pointers, which do not have a stable ABI.
+/// This type splits a deferred slice into a pointer and a length with a stable `#[repr(C)]` layout,
+/// equivalent to the following C struct (for `T = uint8_t`):
+/// ```c
+/// struct DeferredSliceRaw {
+///     const uint8_t *ptr;
+///     size_t len;
+/// };
+/// ```
+/// The conversion from a `Deferred<&[T]>` to a `DeferredSliceRaw<T>` is safe and does not create
+/// any references. The conversion back is lossless, but it is unsafe, because the lifetime of the
+/// deferred reference is lost when crossing the FFI boundary.
+///
+/// # Example
+/// ```
+/// use deferred_reference::DeferredSliceRaw;
+/// extern "C" fn sum(raw: DeferredSliceRaw<u32>) -> u32 {
+///     // SAFETY: the caller promises that `raw` originates from a `Deferred<&[u32]>`
+///     // SAFETY: which outlives this call.
+///     let deferred = unsafe { raw.into_deferred() };
+///     deferred.iter().sum()
+/// }
+/// let buffer = [1u32, 2, 3];
+/// assert_eq!(6, sum(DeferredSliceRaw::from(&buffer[..])));
+/// ```
+///
+/// # Panics
+/// The conversion from a `Deferred<&[T]>` needs the length of the slice.
+/// See [Deferred::len] for when this can panic. The conversion from a `&[T]` works on stable Rust.
+#[repr(C)]
+#[derive(Debug)]
+pub struct DeferredSliceRaw<T> {
+    /// A pointer to the first element of the slice.
+    pub ptr: *const T,
+    /// The number of elements in the slice.
+    pub len: usize,
+}
+
+/// A C-compatible representation of a deferred mutable reference to a slice (i.e. a `Deferred<&mut [T]>`).
+/// See [DeferredSliceRaw] for more details. This type is equivalent to the following C struct
+/// (for `T = uint8_t`):
+/// ```c
+/// struct DeferredSliceMutRaw {
+///     uint8_t *ptr;
+///     size_t len;
+/// };
+/// ```
+///
+/// # Example
+/// ```
+/// use deferred_reference::DeferredSliceMutRaw;
+/// extern "C" fn fill(raw: DeferredSliceMutRaw<u8>, value: u8) {
+///     // SAFETY: the caller promises that `raw` originates from a `Deferred<&mut [u8]>`
+///     // SAFETY: which outlives this call and that no other references to it exist.
+///     let mut deferred = unsafe { raw.into_deferred_mut() };
+///     // note: `deferred.len()` and `deferred[i]` would need the `slice_ptr_len` feature here.
+///     deferred.iter_mut().for_each(|element| *element = value);
+/// }
+/// let mut buffer = [0u8; 4];
+/// fill(DeferredSliceMutRaw::from(&mut buffer[2..]), 42);
+/// assert_eq!([0, 0, 42, 42], buffer);
+/// ```
+///
+/// # Panics
+/// The conversion from a `Deferred<&mut [T]>` needs the length of the slice.
+/// See [Deferred::len] for when this can panic. The conversion from a `&mut [T]` works on stable Rust.
+#[repr(C)]
+#[derive(Debug)]
+pub struct DeferredSliceMutRaw<T> {
+    /// A pointer to the first element of the slice.
+    pub ptr: *mut T,
+    /// The number of elements in the slice.
+    pub len: usize,
+}
+
+impl<T> DeferredSliceRaw<T> {
+    /// Reconstructs the deferred immutable reference to the slice. The lifetime of the returned
+    /// `Deferred` is unbounded, see [DeferredSliceRaw::into_deferred_bounded] for a way to bound it.
+    ///
+    /// # Safety
+    /// The caller must uphold the same guarantees as for [Deferred::from_raw_parts]. In particular,
+    /// `ptr` must be non-null and properly aligned, even if `len` is zero (use [NonNull::dangling](core::ptr::NonNull::dangling)
+    /// for empty slices instead of a null pointer). This is always the case if `self` was obtained from a
+    /// `Deferred<&[T]>` which is still valid.
+    pub unsafe fn into_deferred<'a>(self) -> Deferred<&'a [T]> {
+        Deferred::from_raw_parts(self.ptr, self.len)
+    }
+
+    /// Reconstructs the deferred immutable reference to the slice, with its lifetime bound to the
+    /// lifetime of `owner`. The owner is usually the buffer (or the [UnsafeCell](core::cell::UnsafeCell)
+    /// wrapping the buffer) that the slice points into, so that the returned `Deferred` can not outlive it.
+    ///
+    /// # Safety
+    /// See [DeferredSliceRaw::into_deferred]. Additionally, the memory that `self` points to must remain
+    /// valid for as long as `owner` is borrowed.
+    pub unsafe fn into_deferred_bounded<O: ?Sized>(self, _owner: &O) -> Deferred<&[T]> {
+        self.into_deferred()
+    }
+}
+
+impl<T> DeferredSliceMutRaw<T> {
+    /// Reconstructs the deferred mutable reference to the slice. The lifetime of the returned
+    /// `Deferred` is unbounded, see [DeferredSliceMutRaw::into_deferred_mut_bounded] for a way to bound it.
+    ///
+    /// # Safety
+    /// The caller must uphold the same guarantees as for [Deferred::from_raw_parts_mut]. In particular,
+    /// `ptr` must be non-null and properly aligned, even if `len` is zero (use [NonNull::dangling](core::ptr::NonNull::dangling)
+    /// for empty slices instead of a null pointer). The caller must also ensure that the returned `Deferred`
+    /// is not dereferenced in a way that overlaps with other references, just like for [Deferred::clone_unchecked].
+    pub unsafe fn into_deferred_mut<'a>(self) -> Deferred<&'a mut [T]> {
+        Deferred::from_raw_parts_mut(self.ptr, self.len)
+    }
+
+    /// Reconstructs the deferred mutable reference to the slice, with its lifetime bound to the
+    /// lifetime of `owner`. The owner is usually the buffer (or the [UnsafeCell](core::cell::UnsafeCell)
+    /// wrapping the buffer) that the slice points into, so that the returned `Deferred` can not outlive it.
+    /// Note that `owner` is only borrowed immutably, just like with [DeferMut::defer_mut](crate::DeferMut::defer_mut).
+    ///
+    /// # Safety
+    /// See [DeferredSliceMutRaw::into_deferred_mut]. Additionally, the memory that `self` points to must
+    /// remain valid for as long as `owner` is borrowed.
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn into_deferred_mut_bounded<O: ?Sized>(self, _owner: &O) -> Deferred<&mut [T]> {
+        self.into_deferred_mut()
+    }
+}
+
+// raw pointers are always `Copy`, so these do not need the `T: Copy` bound that `#[derive]` would add.
+impl<T> Copy for DeferredSliceRaw<T> {}
+impl<T> Clone for DeferredSliceRaw<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for DeferredSliceMutRaw<T> {}
+impl<T> Clone for DeferredSliceMutRaw<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> From<Deferred<&[T]>> for DeferredSliceRaw<T> {
+    fn from(deferred: Deferred<&[T]>) -> Self {
+        Self {
+            ptr: deferred.as_ptr() as *const T,
+            len: deferred.len(),
+        }
+    }
+}
+
+impl<T> From<Deferred<&mut [T]>> for DeferredSliceRaw<T> {
+    fn from(deferred: Deferred<&mut [T]>) -> Self {
+        Self::from(deferred.into_ref())
+    }
+}
+
+impl<T> From<Deferred<&mut [T]>> for DeferredSliceMutRaw<T> {
+    fn from(deferred: Deferred<&mut [T]>) -> Self {
+        Self {
+            ptr: deferred.as_mut_ptr() as *mut T,
+            len: deferred.len(),
+        }
+    }
+}
+
+impl<T> From<&[T]> for DeferredSliceRaw<T> {
+    fn from(slice: &[T]) -> Self {
+        Self {
+            ptr: slice.as_ptr(),
+            len: slice.len(),
+        }
+    }
+}
+
+impl<T> From<&mut [T]> for DeferredSliceMutRaw<T> {
+    fn from(slice: &mut [T]) -> Self {
+        Self {
+            ptr: slice.as_mut_ptr(),
+            len: slice.len(),
+        }
+    }
+}
+
+impl<T> From<DeferredSliceMutRaw<T>> for DeferredSliceRaw<T> {
+    fn from(raw: DeferredSliceMutRaw<T>) -> Self {
+        Self {
+            ptr: raw.ptr as *const T,
+            len: raw.len,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ops::Deref;
+    use crate::{DeferredSliceMutRaw, DeferredSliceRaw};
+
+    /// Tests that the layout matches a C struct with a pointer and a length.
+    #[test]
+    fn layout() {
+        assert_eq!(2 * core::mem::size_of::<usize>(), core::mem::size_of::<DeferredSliceRaw<u64>>());
+        assert_eq!(core::mem::align_of::<usize>(), core::mem::align_of::<DeferredSliceMutRaw<u8>>());
+    }
+
+    #[cfg(feature = "slice_ptr_len")]
+    #[test]
+    fn round_trip() {
+        use core::cell::UnsafeCell;
+        use crate::{DeferMut, Deferred};
+        let buffer = UnsafeCell::new([1u16, 2, 3, 4]);
+        let deferred: Deferred<&mut [u16]> = unsafe { buffer.defer_mut() }.into();
+        let ptr = deferred.as_ptr();
+        let raw = DeferredSliceMutRaw::from(deferred);
+        assert_eq!(4, raw.len);
+        let deferred = unsafe { raw.into_deferred_mut_bounded(&buffer) };
+        assert_eq!(ptr, deferred.as_ptr());
+        let raw = DeferredSliceRaw::from(deferred);
+        let deferred = unsafe { raw.into_deferred_bounded(&buffer) };
+        assert_eq!(ptr, deferred.as_ptr());
+        assert_eq!(&[1, 2, 3, 4], deferred.deref());
+    }
+
+    #[cfg(feature = "slice_ptr_len")]
+    #[test]
+    fn callback() {
+        use core::cell::UnsafeCell;
+        use crate::{DeferMut, Deferred};
+        extern "C" fn write(raw: DeferredSliceMutRaw<u32>, value: u32) {
+            let mut deferred = unsafe { raw.into_deferred_mut() };
+            deferred[0] = value;
+        }
+        let buffer = UnsafeCell::new([0u32; 4]);
+        let mut deferred: Deferred<&mut [u32]> = unsafe { buffer.defer_mut() }.into();
+        let (mut left, right) = deferred.split_at_mut(2);
+        // the mutable reference to the left half is alive while the callback writes to the right half
+        let left_ref = &mut left[..];
+        write(right.into(), 42);
+        left_ref[0] = 1;
+        assert_eq!([1, 0, 42, 0], unsafe { *buffer.get() });
+    }
+
+    #[test]
+    fn from_slice() {
+        let mut buffer = [1u8, 2, 3];
+        let raw = DeferredSliceMutRaw::from(&mut buffer[1..]);
+        assert_eq!(2, raw.len);
+        unsafe { raw.into_deferred_mut() }.copy_from_slice(&[4, 5]);
+        let raw = DeferredSliceRaw::from(&buffer[..]);
+        assert_eq!(&[1, 4, 5], unsafe { raw.into_deferred() }.deref());
+    }
+
+    #[test]
+    fn empty() {
+        let raw = DeferredSliceRaw { ptr: core::ptr::NonNull::<u8>::dangling().as_ptr(), len: 0 };
+        let deferred = unsafe { raw.into_deferred() };
+        assert!(deferred.deref().is_empty());
+    }
+}
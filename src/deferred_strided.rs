@@ -0,0 +1,406 @@
+//! This module contains the [DeferredStrided] view on every n-th element of a deferred array or slice.
+
+use core::marker::PhantomData;
+use core::ops::{Index, IndexMut};
+use core::ptr::NonNull;
+
+use crate::{Deferred, Reference, SliceLike};
+
+/// A deferred view on every `stride`-th element of an array or slice, starting at an offset.
+/// Just like [Deferred], this comes in two flavors: `DeferredStrided<&T>` for immutable access and
+/// `DeferredStrided<&mut T>` for mutable access, where `T` is the type of the elements. Indexing and
+/// iterating only create references to the elements in the view, never to the elements in between.
+/// This allows for accessing e.g. the columns of a matrix stored in row-major order, or the individual
+/// channels of interleaved audio samples, while the other columns or channels are accessed at the same time.
+///
+/// Strided views are obtained with [Deferred::strided] and [Deferred::strided_mut] and they can
+/// be split further into disjoint interleaved views with [DeferredStrided::split_interleaved].
+///
+/// # Example
+/// ```
+/// use deferred_reference::Deferred;
+/// // a 3x4 matrix in row-major order
+/// let mut matrix = [
+///     0, 1, 2, 3,
+///     4, 5, 6, 7,
+///     8, 9, 10, 11,
+/// ];
+/// {
+///     let mut deferred = Deferred::from(&mut matrix);
+///     let mut columns = deferred.strided_mut(0, 1).split_interleaved(4);
+///     let mut first = columns.next().unwrap();
+///     let second = columns.next().unwrap();
+///     assert_eq!(3, first.len());
+///     assert_eq!(vec![1, 5, 9], second.iter().copied().collect::<Vec<_>>());
+///     first[2] = 42; // while `second` is alive
+/// }
+/// assert_eq!(42, matrix[8]);
+/// ```
+pub struct DeferredStrided<T>
+where
+    T: Reference,
+    T::Target: Sized,
+{
+    /// A pointer to the first element of the view.
+    ptr: NonNull<T::Target>,
+    /// The number of elements in the view.
+    len: usize,
+    /// The distance between two consecutive elements of the view, measured in elements.
+    stride: usize,
+    /// Inherits the variance, auto traits and lifetime from the reference type.
+    _marker: PhantomData<T>,
+}
+
+// SAFETY: this is safe, because we merely inherit the Sync trait bounds from the Rust reference types.
+unsafe impl<T: Sync + Reference> Sync for DeferredStrided<T> where T::Target: Sized {}
+
+// SAFETY: this is safe, because we merely inherit the Send trait bounds from the Rust reference types.
+unsafe impl<T: Send + Reference> Send for DeferredStrided<T> where T::Target: Sized {}
+
+impl<T> Copy for DeferredStrided<&T> {}
+impl<T> Clone for DeferredStrided<&T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+/// Computes the number of elements in a view starting at `offset` with the given `stride`
+/// into an array or slice of length `len`.
+///
+/// # Panics
+/// Panics if `stride` is zero or if `offset` is larger than `len`.
+#[track_caller]
+fn strided_len(len: usize, offset: usize, stride: usize) -> usize {
+    assert!(stride != 0, "stride must be non-zero");
+    assert!(offset <= len, "offset {} out of range for slice pointer of length {}", offset, len);
+    if offset == len { 0 } else { (len - offset - 1) / stride + 1 }
+}
+
+/// # Methods for creating strided views on deferred references to slices and arrays
+impl<T> Deferred<T>
+where
+    T: Reference,
+    T::Target: SliceLike,
+{
+    /// Returns a view on every `stride`-th element of the array or slice, starting at the element at index `offset`.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let samples = [1, -1, 2, -2, 3, -3]; // interleaved stereo samples
+    /// let deferred = Deferred::new(&samples);
+    /// let right = deferred.strided(1, 2);
+    /// assert_eq!(3, right.len());
+    /// assert_eq!(-2, right[1]);
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if `stride` is zero or if `offset` is larger than the length of the array or slice.
+    /// This method also needs the length of the slice. See [Deferred::len] for when this can panic.
+    #[track_caller]
+    pub fn strided(&self, offset: usize, stride: usize) -> DeferredStrided<&<T::Target as SliceLike>::Element> {
+        let len = strided_len(self.len(), offset, stride);
+        // SAFETY: `offset` is in bounds (or one past the end of the array or slice).
+        let ptr = unsafe { (self.as_ptr() as *const <T::Target as SliceLike>::Element).add(offset) };
+        DeferredStrided {
+            // SAFETY: the pointer is derived from a non-null pointer.
+            ptr: unsafe { NonNull::new_unchecked(ptr as *mut _) },
+            len,
+            stride,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// # Methods for creating strided views on deferred _mutable_ references to slices and arrays
+impl<T> Deferred<&mut T>
+where
+    T: SliceLike + ?Sized,
+{
+    /// Returns a mutable view on every `stride`-th element of the array or slice, starting at the element at index `offset`.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let mut samples = [1, -1, 2, -2, 3, -3]; // interleaved stereo samples
+    /// let mut deferred = Deferred::from(&mut samples);
+    /// for sample in deferred.strided_mut(1, 2).iter_mut() {
+    ///     *sample = 0; // mute the right channel
+    /// }
+    /// assert_eq!([1, 0, 2, 0, 3, 0], samples);
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if `stride` is zero or if `offset` is larger than the length of the array or slice.
+    /// This method also needs the length of the slice. See [Deferred::len] for when this can panic.
+    #[track_caller]
+    pub fn strided_mut(&mut self, offset: usize, stride: usize) -> DeferredStrided<&mut T::Element> {
+        let len = strided_len(self.len(), offset, stride);
+        // SAFETY: `offset` is in bounds (or one past the end of the array or slice).
+        let ptr = unsafe { (self.as_mut_ptr() as *mut T::Element).add(offset) };
+        DeferredStrided {
+            // SAFETY: the pointer is derived from a non-null pointer.
+            ptr: unsafe { NonNull::new_unchecked(ptr) },
+            len,
+            stride,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> DeferredStrided<T>
+where
+    T: Reference,
+    T::Target: Sized,
+{
+    /// Creates a view from a pointer to its first element, its length and its stride.
+    ///
+    /// # Safety
+    /// The pointer must be valid for the lifetime of `T` and all of the elements of the view must
+    /// lie within the same allocated object. Mutable views may not overlap with other references.
+    pub(crate) unsafe fn from_raw_parts(ptr: NonNull<T::Target>, len: usize, stride: usize) -> Self {
+        Self {
+            ptr,
+            len,
+            stride,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the number of elements in the view.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the view contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the distance between two consecutive elements of the view, measured in elements.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Returns a raw pointer to the element at `index`, without dereferencing it.
+    ///
+    /// # Safety
+    /// `index` must be smaller than `self.len()`.
+    unsafe fn ptr_at(&self, index: usize) -> *mut T::Target {
+        self.ptr.as_ptr().add(index * self.stride)
+    }
+
+    /// Returns a reference to the element at `index`, or `None` if `index` is out of bounds.
+    /// No references to the other elements are created.
+    pub fn get(&self, index: usize) -> Option<&T::Target> {
+        if index < self.len {
+            // SAFETY: `index` is in bounds.
+            Some(unsafe { &*self.ptr_at(index) })
+        } else {
+            None
+        }
+    }
+
+    /// Returns an iterator over references to the elements of the view.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T::Target> + ExactSizeIterator {
+        // SAFETY: all indices are in bounds.
+        (0..self.len).map(move |index| unsafe { &*self.ptr_at(index) })
+    }
+
+    /// Splits the view into `count` disjoint interleaved views, such that the `i`-th view contains the
+    /// elements at indices `i`, `i + count`, `i + 2 * count` etc. of this view. This consumes the view,
+    /// so that mutable views never overlap.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let mut samples = [0.5, -0.5, 1.0, -1.0];
+    /// {
+    ///     let mut deferred = Deferred::from(&mut samples);
+    ///     let mut channels = deferred.strided_mut(0, 1).split_interleaved(2);
+    ///     let (mut left, mut right) = (channels.next().unwrap(), channels.next().unwrap());
+    ///     for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+    ///         core::mem::swap(l, r);
+    ///     }
+    /// }
+    /// assert_eq!([-0.5, 0.5, -1.0, 1.0], samples);
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if `count` is zero.
+    #[track_caller]
+    pub fn split_interleaved(self, count: usize) -> impl DoubleEndedIterator<Item = Self> + ExactSizeIterator {
+        assert!(count != 0, "count must be non-zero");
+        let stride = self.stride.checked_mul(count);
+        (0..count).map(move |index| {
+            // note: this is the number of elements at `index`, `index + count`, ..., without overflowing for large `count`
+            let len = if index < self.len { (self.len - 1 - index) / count + 1 } else { 0 };
+            DeferredStrided {
+                // SAFETY: `index` is in bounds of `self` if the new view is not empty.
+                ptr: if len == 0 { self.ptr } else { unsafe { NonNull::new_unchecked(self.ptr_at(index)) } },
+                len,
+                // the stride can only overflow if the new view contains at most one element
+                stride: stride.unwrap_or(1),
+                _marker: PhantomData,
+            }
+        })
+    }
+}
+
+impl<T> DeferredStrided<&mut T> {
+    /// Returns a mutable reference to the element at `index`, or `None` if `index` is out of bounds.
+    /// No references to the other elements are created.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index < self.len {
+            // SAFETY: `index` is in bounds.
+            Some(unsafe { &mut *self.ptr_at(index) })
+        } else {
+            None
+        }
+    }
+
+    /// Returns an iterator over mutable references to the elements of the view.
+    pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut T> + ExactSizeIterator {
+        let ptr = self.ptr;
+        let stride = self.stride;
+        // SAFETY: all indices are in bounds and every element is only handed out once.
+        (0..self.len).map(move |index| unsafe { &mut *ptr.as_ptr().add(index * stride) })
+    }
+}
+
+impl<'a, T> DeferredStrided<&'a mut T> {
+    /// Converts this mutable view into an immutable view.
+    pub fn into_ref(self) -> DeferredStrided<&'a T> {
+        DeferredStrided {
+            ptr: self.ptr,
+            len: self.len,
+            stride: self.stride,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Index<usize> for DeferredStrided<T>
+where
+    T: Reference,
+    T::Target: Sized,
+{
+    type Output = T::Target;
+
+    #[track_caller]
+    fn index(&self, index: usize) -> &Self::Output {
+        match self.get(index) {
+            Some(element) => element,
+            None => panic!("index {} out of range for strided view of length {}", index, self.len),
+        }
+    }
+}
+
+impl<T> IndexMut<usize> for DeferredStrided<&mut T> {
+    #[track_caller]
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        let len = self.len;
+        match self.get_mut(index) {
+            Some(element) => element,
+            None => panic!("index {} out of range for strided view of length {}", index, len),
+        }
+    }
+}
+
+impl<T> core::fmt::Debug for DeferredStrided<T>
+where
+    T: Reference,
+    T::Target: Sized + core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::cell::UnsafeCell;
+    use crate::{Defer, DeferMut, Deferred};
+
+    #[test]
+    fn len() {
+        let buffer = [0u8; 10];
+        let deferred = Deferred::new(&buffer);
+        assert_eq!(10, deferred.strided(0, 1).len());
+        assert_eq!(4, deferred.strided(0, 3).len());
+        assert_eq!(3, deferred.strided(1, 3).len());
+        assert_eq!(1, deferred.strided(9, 3).len());
+        assert_eq!(1, deferred.strided(0, usize::MAX).len());
+        assert!(deferred.strided(10, 3).is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_stride() {
+        let buffer = [0u8; 10];
+        Deferred::new(&buffer).strided(0, 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn offset_out_of_bounds() {
+        let buffer = [0u8; 10];
+        Deferred::new(&buffer).strided(11, 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_out_of_bounds() {
+        let buffer = [0u8; 10];
+        let _ = Deferred::new(&buffer).strided(1, 2)[5];
+    }
+
+    #[test]
+    fn columns() {
+        let buffer = UnsafeCell::new([0usize; 12]);
+        let mut deferred = unsafe { buffer.defer_mut() };
+        let columns = deferred.strided_mut(0, 1).split_interleaved(3);
+        assert_eq!(3, columns.len());
+        let mut columns: Vec<_> = columns.collect();
+        // all columns are mutated at the same time while an element is being read
+        let mut refs: Vec<Vec<&mut usize>> = columns.iter_mut().map(|column| column.iter_mut().collect()).collect();
+        for (column, elements) in refs.iter_mut().enumerate() {
+            assert_eq!(4, elements.len());
+            for (row, element) in elements.iter_mut().enumerate() {
+                **element = row * 3 + column;
+            }
+        }
+        let values: Vec<usize> = unsafe { &*buffer.get() }.to_vec();
+        assert_eq!((0..12).collect::<Vec<_>>(), values);
+        let column = columns.pop().unwrap().into_ref();
+        assert_eq!(alloc::format!("{:?}", column), "[2, 5, 8, 11]");
+        assert_eq!(Some(&8), column.get(2));
+        assert_eq!(None, column.get(4));
+        assert_eq!([11, 8, 5, 2], column.iter().rev().copied().collect::<Vec<_>>()[..]);
+    }
+
+    #[test]
+    fn split_uneven() {
+        let buffer = UnsafeCell::new([0u8, 1, 2, 3, 4, 5, 6]);
+        let deferred = buffer.defer();
+        let view = deferred.strided(1, 2); // 1, 3, 5
+        let lens: Vec<_> = view.split_interleaved(5).map(|part| part.len()).collect();
+        assert_eq!([1, 1, 1, 0, 0], lens[..]);
+        let parts: Vec<_> = view.split_interleaved(2).collect();
+        assert_eq!(4, parts[0].stride());
+        assert_eq!([1, 5], parts[0].iter().copied().collect::<Vec<_>>()[..]);
+        assert_eq!([3], parts[1].iter().copied().collect::<Vec<_>>()[..]);
+    }
+
+    #[test]
+    fn split_huge_count() {
+        let buffer = [0u8, 1, 2, 3];
+        let deferred = Deferred::new(&buffer);
+        let mut parts = deferred.strided(0, 1).split_interleaved(usize::MAX);
+        assert_eq!(usize::MAX, parts.len());
+        let lens: Vec<_> = parts.by_ref().take(5).map(|part| part.len()).collect();
+        assert_eq!([1, 1, 1, 1, 0], lens[..]);
+        assert_eq!(Some(0), parts.next_back().map(|part| part.len()));
+    }
+}
@@ -0,0 +1,174 @@
+//! This module contains the endian-aware integer accessors for deferred references to byte slices and byte arrays.
+
+use crate::{Deferred, Reference, SliceLike};
+
+/// Generates the endian-aware read methods for the given integer types, with the given documentation.
+macro_rules! impl_read_endian {
+    ($($ty:ident: $le:ident => $le_doc:expr, $be:ident => $be_doc:expr;)*) => {
+        $(
+            #[doc = $le_doc]
+            ///
+            /// # Panics
+            /// Panics if the integer does not lie within the array or slice.
+            /// For slices this method also needs the length of the slice. See [Deferred::len] for when this can panic.
+            #[track_caller]
+            pub fn $le(&self, offset: usize) -> $ty {
+                $ty::from_le(self.read_unaligned(offset))
+            }
+
+            #[doc = $be_doc]
+            ///
+            /// # Panics
+            /// Panics if the integer does not lie within the array or slice.
+            /// For slices this method also needs the length of the slice. See [Deferred::len] for when this can panic.
+            #[track_caller]
+            pub fn $be(&self, offset: usize) -> $ty {
+                $ty::from_be(self.read_unaligned(offset))
+            }
+        )*
+    };
+}
+
+/// Generates the endian-aware write methods for the given integer types, with the given documentation.
+macro_rules! impl_write_endian {
+    ($($ty:ident: $le:ident => $le_doc:expr, $be:ident => $be_doc:expr;)*) => {
+        $(
+            #[doc = $le_doc]
+            ///
+            /// # Panics
+            /// Panics if the integer does not lie within the array or slice.
+            /// For slices this method also needs the length of the slice. See [Deferred::len] for when this can panic.
+            #[track_caller]
+            pub fn $le(&mut self, offset: usize, value: $ty) {
+                self.write_unaligned(offset, value.to_le())
+            }
+
+            #[doc = $be_doc]
+            ///
+            /// # Panics
+            /// Panics if the integer does not lie within the array or slice.
+            /// For slices this method also needs the length of the slice. See [Deferred::len] for when this can panic.
+            #[track_caller]
+            pub fn $be(&mut self, offset: usize, value: $ty) {
+                self.write_unaligned(offset, value.to_be())
+            }
+        )*
+    };
+}
+
+/// # Methods for reading integers from deferred references to byte slices and byte arrays
+/// These methods read integers of a fixed endianness at arbitrary byte offsets, e.g. the fields of a
+/// network packet header. Only the bytes of the integer are touched, so different fields of the same
+/// buffer can be accessed at the same time.
+///
+/// # Example
+/// ```
+/// use deferred_reference::Deferred;
+/// let packet = [0x45u8, 0x00, 0x00, 0x54, 0x12, 0x34];
+/// let deferred = Deferred::new(&packet);
+/// assert_eq!(0x54, deferred.read_u16_be(2));
+/// assert_eq!(0x3412_5400, deferred.read_u32_le(2));
+/// ```
+impl<T> Deferred<T>
+where
+    T: Reference,
+    T::Target: SliceLike<Element = u8>,
+{
+    impl_read_endian! {
+        u16: read_u16_le => "Reads a little endian `u16` at byte offset `offset`, without creating any references.",
+            read_u16_be => "Reads a big endian `u16` at byte offset `offset`, without creating any references.";
+        u32: read_u32_le => "Reads a little endian `u32` at byte offset `offset`, without creating any references.",
+            read_u32_be => "Reads a big endian `u32` at byte offset `offset`, without creating any references.";
+        u64: read_u64_le => "Reads a little endian `u64` at byte offset `offset`, without creating any references.",
+            read_u64_be => "Reads a big endian `u64` at byte offset `offset`, without creating any references.";
+        u128: read_u128_le => "Reads a little endian `u128` at byte offset `offset`, without creating any references.",
+            read_u128_be => "Reads a big endian `u128` at byte offset `offset`, without creating any references.";
+        i16: read_i16_le => "Reads a little endian `i16` at byte offset `offset`, without creating any references.",
+            read_i16_be => "Reads a big endian `i16` at byte offset `offset`, without creating any references.";
+        i32: read_i32_le => "Reads a little endian `i32` at byte offset `offset`, without creating any references.",
+            read_i32_be => "Reads a big endian `i32` at byte offset `offset`, without creating any references.";
+        i64: read_i64_le => "Reads a little endian `i64` at byte offset `offset`, without creating any references.",
+            read_i64_be => "Reads a big endian `i64` at byte offset `offset`, without creating any references.";
+        i128: read_i128_le => "Reads a little endian `i128` at byte offset `offset`, without creating any references.",
+            read_i128_be => "Reads a big endian `i128` at byte offset `offset`, without creating any references.";
+    }
+}
+
+/// # Methods for writing integers to deferred _mutable_ references to byte slices and byte arrays
+///
+/// # Example
+/// ```
+/// use deferred_reference::Deferred;
+/// let mut packet = [0u8; 6];
+/// let mut deferred = Deferred::from(&mut packet);
+/// deferred.write_u16_be(0, 0x0800);
+/// deferred.write_u32_le(2, 0x0403_0201);
+/// assert_eq!([0x08, 0x00, 0x01, 0x02, 0x03, 0x04], packet);
+/// ```
+impl<T> Deferred<&mut T>
+where
+    T: SliceLike<Element = u8> + ?Sized,
+{
+    impl_write_endian! {
+        u16: write_u16_le => "Writes `value` as a little endian `u16` at byte offset `offset`, without creating any references.",
+            write_u16_be => "Writes `value` as a big endian `u16` at byte offset `offset`, without creating any references.";
+        u32: write_u32_le => "Writes `value` as a little endian `u32` at byte offset `offset`, without creating any references.",
+            write_u32_be => "Writes `value` as a big endian `u32` at byte offset `offset`, without creating any references.";
+        u64: write_u64_le => "Writes `value` as a little endian `u64` at byte offset `offset`, without creating any references.",
+            write_u64_be => "Writes `value` as a big endian `u64` at byte offset `offset`, without creating any references.";
+        u128: write_u128_le => "Writes `value` as a little endian `u128` at byte offset `offset`, without creating any references.",
+            write_u128_be => "Writes `value` as a big endian `u128` at byte offset `offset`, without creating any references.";
+        i16: write_i16_le => "Writes `value` as a little endian `i16` at byte offset `offset`, without creating any references.",
+            write_i16_be => "Writes `value` as a big endian `i16` at byte offset `offset`, without creating any references.";
+        i32: write_i32_le => "Writes `value` as a little endian `i32` at byte offset `offset`, without creating any references.",
+            write_i32_be => "Writes `value` as a big endian `i32` at byte offset `offset`, without creating any references.";
+        i64: write_i64_le => "Writes `value` as a little endian `i64` at byte offset `offset`, without creating any references.",
+            write_i64_be => "Writes `value` as a big endian `i64` at byte offset `offset`, without creating any references.";
+        i128: write_i128_le => "Writes `value` as a little endian `i128` at byte offset `offset`, without creating any references.",
+            write_i128_be => "Writes `value` as a big endian `i128` at byte offset `offset`, without creating any references.";
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::UnsafeCell;
+    use crate::{DeferMut, Deferred};
+
+    #[test]
+    fn round_trip() {
+        let mut buffer = [0u8; 40];
+        let mut deferred = Deferred::from(&mut buffer);
+        deferred.write_u16_le(1, 0x0102);
+        deferred.write_i32_be(3, -2);
+        deferred.write_u64_be(7, 0x0102_0304_0506_0708);
+        deferred.write_i128_le(15, i128::MIN + 1);
+        assert_eq!(0x0102, deferred.read_u16_le(1));
+        assert_eq!(0x0201, deferred.read_u16_be(1));
+        assert_eq!(-2, deferred.read_i32_be(3));
+        assert_eq!(0x0102_0304_0506_0708, deferred.read_u64_be(7));
+        assert_eq!(i128::MIN + 1, deferred.read_i128_le(15));
+        assert_eq!([0x02, 0x01], buffer[1..3]);
+        assert_eq!([0xff, 0xff, 0xff, 0xfe], buffer[3..7]);
+        assert_eq!([1, 2, 3, 4, 5, 6, 7, 8], buffer[7..15]);
+    }
+
+    #[test]
+    fn concurrent_fields() {
+        let buffer = UnsafeCell::new([0u8; 8]);
+        let mut deferred = unsafe { buffer.defer_mut() };
+        let mut other = unsafe { deferred.clone_unchecked() };
+        extern crate std;
+        std::thread::scope(|scope| {
+            scope.spawn(move || deferred.write_u32_be(0, 0xdead_beef));
+            scope.spawn(move || other.write_u32_le(4, 0xdead_beef));
+        });
+        assert_eq!([0xde, 0xad, 0xbe, 0xef, 0xef, 0xbe, 0xad, 0xde], unsafe { *buffer.get() });
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_bounds() {
+        let mut buffer = [0u8; 8];
+        Deferred::from(&mut buffer).write_u16_be(7, 0);
+    }
+}
@@ -0,0 +1,311 @@
+//! This module contains the [IntrusiveList] struct, a doubly-linked list whose links are deferred mutable references.
+
+use core::marker::PhantomData;
+use core::ptr::{addr_of, addr_of_mut};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::Deferred;
+
+/// The identifier of the next [IntrusiveList], which is used to check that a [ListHandle] belongs to a list.
+static NEXT_LIST_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A deferred mutable reference to a node of an [IntrusiveList].
+type Link<'a, T> = Deferred<&'a mut ListNode<'a, T>>;
+
+/// Makes a copy of a link, without dereferencing it.
+fn copy<'a, T>(link: &Link<'a, T>) -> Link<'a, T> {
+    // SAFETY: the copies of a link are only dereferenced by the list, one at a time.
+    unsafe { link.clone_unchecked() }
+}
+
+/// Makes a copy of an optional link, without dereferencing it.
+fn copy_link<'a, T>(link: &Option<Link<'a, T>>) -> Option<Link<'a, T>> {
+    link.as_ref().map(copy)
+}
+
+/// A node of an [IntrusiveList], which embeds a value and the links to the previous and the next node.
+/// Nodes are stored outside of the list, e.g. in an array or in a [DeferredArena](crate::DeferredArena),
+/// and they are linked into the list by handing over a [`Deferred<&mut ListNode>`](Deferred).
+pub struct ListNode<'a, T> {
+    /// The value of the node.
+    value: T,
+    /// The link to the previous node, if any.
+    prev: Option<Link<'a, T>>,
+    /// The link to the next node, if any.
+    next: Option<Link<'a, T>>,
+}
+
+impl<T> ListNode<'_, T> {
+    /// Creates a new node which is not linked into any list.
+    pub const fn new(value: T) -> Self {
+        Self { value, prev: None, next: None }
+    }
+
+    /// Returns a reference to the value of the node.
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    /// Returns a mutable reference to the value of the node.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+
+    /// Consumes the node and returns its value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for ListNode<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("ListNode").field(&self.value).finish()
+    }
+}
+
+/// A handle to a node of an [IntrusiveList], which is returned when the node is pushed onto the list
+/// and which is consumed when the node is removed from the list again with [IntrusiveList::remove].
+pub struct ListHandle<'a, T> {
+    /// The node, which must not be dereferenced by the handle.
+    node: Link<'a, T>,
+    /// The identifier of the list that the node is linked into.
+    list: usize,
+}
+
+impl<T> core::fmt::Debug for ListHandle<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // the node is owned by the list, so only its address is printed
+        f.debug_tuple("ListHandle").field(&self.node.as_ptr()).finish()
+    }
+}
+
+/// An intrusive doubly-linked list, whose nodes are stored outside of the list and whose links are deferred
+/// mutable references (i.e. `Option<Deferred<&mut ListNode>>`). Every node is pointed to by up to three deferred
+/// mutable references at the same time (by its neighbours and by its [ListHandle]). The list upholds the no-overlap
+/// invariant of these deferred references internally: it only ever dereferences one of them at a time,
+/// so all of its methods are safe. Pushing and removing nodes takes constant time and does not allocate any memory.
+///
+/// # Example
+/// ```
+/// use deferred_reference::{DeferredArena, IntrusiveList, ListNode};
+/// let mut nodes = [ListNode::new(0), ListNode::new(0), ListNode::new(0), ListNode::new(0)];
+/// let arena = DeferredArena::from(&mut nodes[..]);
+/// let mut list = IntrusiveList::new();
+/// let handles: Vec<_> = (1..=4).map(|value| {
+///     let mut node = arena.alloc().unwrap();
+///     *node.get_mut() = value;
+///     list.push_back(node)
+/// }).collect();
+/// let mut handles = handles.into_iter();
+/// let second = list.remove(handles.nth(1).unwrap());
+/// assert_eq!(&2, second.get());
+/// list.iter_mut().for_each(|value| *value *= 10);
+/// list.push_front(second);
+/// assert_eq!(vec![2, 10, 30, 40], list.iter().copied().collect::<Vec<_>>());
+/// ```
+pub struct IntrusiveList<'a, T> {
+    /// The identifier of the list, which is unique for every list.
+    id: usize,
+    /// The first node of the list.
+    head: Option<Link<'a, T>>,
+    /// The last node of the list.
+    tail: Option<Link<'a, T>>,
+    /// The number of nodes in the list.
+    len: usize,
+}
+
+impl<'a, T> IntrusiveList<'a, T> {
+    /// Creates a new empty list.
+    ///
+    /// # Panics
+    /// Panics if more than `usize::MAX` lists have been created, because every list needs a unique identifier.
+    pub fn new() -> Self {
+        let id = NEXT_LIST_ID
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |id| id.checked_add(1))
+            .expect("IntrusiveList: ran out of list identifiers");
+        Self { id, head: None, tail: None, len: 0 }
+    }
+
+    /// Returns the number of nodes in the list.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the list has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns a handle to `node`, which is about to be linked into this list.
+    fn handle(&self, node: &Link<'a, T>) -> ListHandle<'a, T> {
+        ListHandle { node: copy(node), list: self.id }
+    }
+
+    /// Links `node` at the back of the list and returns a handle for removing it again.
+    pub fn push_back(&mut self, mut node: Link<'a, T>) -> ListHandle<'a, T> {
+        let handle = self.handle(&node);
+        node.prev = copy_link(&self.tail);
+        node.next = None;
+        match &mut self.tail {
+            Some(tail) => tail.next = Some(copy(&node)),
+            None => self.head = Some(copy(&node)),
+        }
+        self.tail = Some(node);
+        self.len += 1;
+        handle
+    }
+
+    /// Links `node` at the front of the list and returns a handle for removing it again.
+    pub fn push_front(&mut self, mut node: Link<'a, T>) -> ListHandle<'a, T> {
+        let handle = self.handle(&node);
+        node.prev = None;
+        node.next = copy_link(&self.head);
+        match &mut self.head {
+            Some(head) => head.prev = Some(copy(&node)),
+            None => self.tail = Some(copy(&node)),
+        }
+        self.head = Some(node);
+        self.len += 1;
+        handle
+    }
+
+    /// Unlinks the node of `handle` from the list and returns the deferred mutable reference to it.
+    ///
+    /// # Panics
+    /// Panics if `handle` was returned by another list.
+    #[track_caller]
+    pub fn remove(&mut self, handle: ListHandle<'a, T>) -> Link<'a, T> {
+        assert!(handle.list == self.id, "IntrusiveList::remove: handle belongs to another list");
+        let mut node = handle.node;
+        let (prev, next) = (node.prev.take(), node.next.take());
+        match &prev {
+            Some(prev) => copy(prev).next = copy_link(&next),
+            None => self.head = copy_link(&next),
+        }
+        match next {
+            Some(mut next) => next.prev = prev,
+            None => self.tail = prev,
+        }
+        self.len -= 1;
+        node
+    }
+
+    /// Returns an iterator over the values of the nodes, from front to back.
+    pub fn iter(&self) -> Iter<'_, 'a, T> {
+        Iter { next: copy_link(&self.head), _marker: PhantomData }
+    }
+
+    /// Returns an iterator over mutable references to the values of the nodes, from front to back.
+    pub fn iter_mut(&mut self) -> IterMut<'_, 'a, T> {
+        IterMut { next: copy_link(&self.head), _marker: PhantomData }
+    }
+}
+
+/// An iterator over the values of an [IntrusiveList], see [IntrusiveList::iter].
+pub struct Iter<'l, 'a, T> {
+    /// The next node to visit.
+    next: Option<Link<'a, T>>,
+    /// The list is borrowed while the iterator is alive.
+    _marker: PhantomData<&'l IntrusiveList<'a, T>>,
+}
+
+impl<'l, T> Iterator for Iter<'l, '_, T> {
+    type Item = &'l T;
+
+    fn next(&mut self) -> Option<&'l T> {
+        let ptr = self.next.take()?.as_ptr();
+        // SAFETY: the list is borrowed, so the nodes can not be mutated while the values are borrowed.
+        unsafe {
+            self.next = copy_link(&(*ptr).next);
+            Some(&(*ptr).value)
+        }
+    }
+}
+
+/// An iterator over mutable references to the values of an [IntrusiveList], see [IntrusiveList::iter_mut].
+pub struct IterMut<'l, 'a, T> {
+    /// The next node to visit.
+    next: Option<Link<'a, T>>,
+    /// The list is mutably borrowed while the iterator is alive.
+    _marker: PhantomData<&'l mut IntrusiveList<'a, T>>,
+}
+
+impl<'l, T> Iterator for IterMut<'l, '_, T> {
+    type Item = &'l mut T;
+
+    fn next(&mut self) -> Option<&'l mut T> {
+        let ptr = self.next.take()?.as_mut_ptr();
+        // SAFETY: the list is mutably borrowed, so the links are not mutated while the values are borrowed.
+        // SAFETY: the mutable references only cover the values, which are disjoint from the links.
+        unsafe {
+            self.next = copy_link(&*addr_of!((*ptr).next));
+            Some(&mut *addr_of_mut!((*ptr).value))
+        }
+    }
+}
+
+impl<T> Default for IntrusiveList<'_, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for IntrusiveList<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use crate::{Deferred, DeferredArena, IntrusiveList, ListNode};
+
+    #[test]
+    fn push_and_remove() {
+        let mut nodes = [(); 5].map(|_| ListNode::new(0));
+        let arena = DeferredArena::from(&mut nodes[..]);
+        let mut list = IntrusiveList::new();
+        let mut handles = Vec::new();
+        for value in 0..5 {
+            let mut node = arena.alloc().unwrap();
+            *node.get_mut() = value;
+            handles.push(if value % 2 == 0 { list.push_back(node) } else { list.push_front(node) });
+        }
+        assert_eq!(alloc::vec![3, 1, 0, 2, 4], list.iter().copied().collect::<Vec<_>>());
+        let mut removed = Vec::new();
+        for index in [4, 3, 0] {
+            removed.push(*list.remove(handles.remove(index)).get());
+        }
+        assert_eq!(alloc::vec![4, 3, 0], removed);
+        assert_eq!(alloc::vec![1, 2], list.iter().copied().collect::<Vec<_>>());
+        for handle in handles.drain(..) {
+            list.remove(handle);
+        }
+        assert!(list.is_empty());
+        assert!(list.iter().next().is_none());
+    }
+
+    #[test]
+    fn iter_mut() {
+        let (mut first, mut second) = (ListNode::new(1), ListNode::new(2));
+        let mut list = IntrusiveList::new();
+        let _first = list.push_back(Deferred::from(&mut first));
+        let _second = list.push_back(Deferred::from(&mut second));
+        let values: Vec<&mut i32> = list.iter_mut().collect();
+        for value in values {
+            *value += 10;
+        }
+        assert_eq!("[11, 12]", alloc::format!("{:?}", list));
+        assert_eq!(2, list.len());
+    }
+
+    #[test]
+    #[should_panic]
+    fn other_list() {
+        let mut node = ListNode::new(0);
+        let mut list = IntrusiveList::new();
+        let handle = list.push_back(Deferred::from(&mut node));
+        IntrusiveList::new().remove(handle);
+    }
+}
@@ -94,7 +94,21 @@
 //!
 //! # `#![no_std]` environments
 //! This crate is entirely `#![no_std]` and does not depend on the `alloc` crate. No additional `Cargo.toml` features need to be configured
-//! in order to support `#![no_std]` environments. This crate also does not have any dependencies in its `Cargo.toml`.
+//! in order to support `#![no_std]` environments. This crate does not have any required dependencies in its `Cargo.toml`.
+//!
+//! # Optional features
+//! * `mmap` (Linux only, implies `std`): the `DeferredMmap` type, which exposes memory-mapped files and anonymous
+//!   mappings as deferred byte slices, and the `SharedRegion` type, which shares deferred slices between processes.
+//!   This adds a dependency on the [`libc`](https://docs.rs/libc) crate.
+//! * `soa` (implies `std`): the `Soa` fixed-capacity struct-of-arrays container and the `#[derive(SoaRecord)]` macro for
+//!   its records, which hands out deferred mutable references to all columns at once through a shared reference.
+//!   This adds a dependency on the `deferred-reference-derive` crate, which has no dependencies of its own.
+//!   The `SoaRecord` trait uses generic associated types, so this feature requires Rust 1.65 or later.
+//! * `std`: the `DeferredCursor` type, which implements the [`std::io`](https://doc.rust-lang.org/std/io/) traits
+//!   on deferred byte slices, the `DeferredOutput` type, which initializes an output buffer in parallel, and the
+//!   `TypedArena` type, which hands out simultaneous deferred mutable references to the values of an arena.
+//! * `ndarray`: conversions between deferred slices and the array views of the [`ndarray`](https://docs.rs/ndarray) crate,
+//!   as well as splitting deferred slices into disjoint lanes along an axis (see `Deferred::lanes`).
 //!
 //! # Miri tested
 //! This crate is extensively tested using [Miri](https://github.com/rust-lang/miri) using the `-Zmiri-track-raw-pointers` flag:
@@ -124,12 +138,22 @@
 // through [Deferred::unsize], because we can't implement [CoerceUnsized] on
 // [Deferred] (yet, this gives compiler errors).
 #![cfg_attr(feature = "coerce_unsized", feature(coerce_unsized))]
+// the `layout_for_ptr` feature is needed to check the alignment of unsized pointees in
+// debug builds of [Deferred::from_raw] and [Deferred::from_raw_mut].
+#![cfg_attr(feature = "layout_for_ptr", feature(layout_for_ptr))]
 
 #![deny(missing_docs)]
 #![forbid(clippy::missing_docs_in_private_items)]
 
+// the `std` crate is only used for the `std` feature, this crate is `#![no_std]` otherwise.
+#[cfg(feature = "std")]
+extern crate std;
+
 // the `alloc` crate is only used for tests, but it is not used by this crate otherwise.
 #[cfg(test)] #[macro_use] extern crate alloc;
+// the code generated by `#[derive(SoaRecord)]` refers to this crate by name, also in its own tests.
+#[cfg(all(test, feature = "soa"))]
+extern crate self as deferred_reference;
 
 
 // from <https://rust-lang.github.io/unsafe-code-guidelines/glossary.html>:
@@ -140,6 +164,9 @@
 // not yet been defined. The purpose of this definition is to define when aliasing happens,
 // not when it is allowed. The most developed potential aliasing model so far is Stacked Borrows."
 
+mod by_address;
+pub use by_address::*;
+
 mod core_traits_impl;
 pub use core_traits_impl::*;
 
@@ -152,12 +179,71 @@ pub use defer_mut::*;
 mod deferred;
 pub use deferred::*;
 
+mod deferred_arena;
+pub use deferred_arena::*;
+
+#[cfg(feature = "std")]
+mod deferred_cursor;
+#[cfg(feature = "std")]
+pub use deferred_cursor::*;
+
+mod deferred_matrix;
+pub use deferred_matrix::*;
+
+#[cfg(all(feature = "mmap", target_os = "linux"))]
+mod deferred_mmap;
+#[cfg(all(feature = "mmap", target_os = "linux"))]
+pub use deferred_mmap::*;
+
+#[cfg(feature = "std")]
+mod deferred_output;
+#[cfg(feature = "std")]
+pub use deferred_output::*;
+
+mod deferred_slice_raw;
+pub use deferred_slice_raw::*;
+
+mod deferred_strided;
+pub use deferred_strided::*;
+
+mod endian_impl;
+
+mod intrusive_list;
+pub use intrusive_list::*;
+
+mod maybe_uninit_impl;
+
+#[cfg(feature = "ndarray")]
+mod ndarray_impl;
+
+mod pin_impl;
+
+mod ping_pong;
+pub use ping_pong::*;
+
+mod pod;
+pub use pod::*;
+
+mod pointer_error;
+pub use pointer_error::*;
+
+mod pointer_layout;
+pub use pointer_layout::*;
+
 mod pointer_length;
 pub use pointer_length::*;
 
 mod reference;
 pub use reference::*;
 
+mod ring_buffer;
+pub use ring_buffer::*;
+
+#[cfg(all(feature = "mmap", target_os = "linux"))]
+mod shared_region;
+#[cfg(all(feature = "mmap", target_os = "linux"))]
+pub use shared_region::*;
+
 mod slice_like;
 pub use slice_like::*;
 
@@ -167,6 +253,29 @@ pub use slice_like_impl::*;
 mod slice_pointer_index;
 pub use slice_pointer_index::*;
 
+mod slot_pool;
+pub use slot_pool::*;
+
+#[cfg(feature = "soa")]
+mod soa;
+#[cfg(feature = "soa")]
+pub use soa::*;
+#[cfg(feature = "soa")]
+pub use deferred_reference_derive::SoaRecord;
+
+mod span_impl;
+
+#[cfg(feature = "std")]
+mod typed_arena;
+#[cfg(feature = "std")]
+pub use typed_arena::*;
+
+mod unaligned_deferred;
+pub use unaligned_deferred::*;
+
+mod write_only;
+pub use write_only::*;
+
 
 #[cfg(test)]
 mod tests {
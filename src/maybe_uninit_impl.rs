@@ -0,0 +1,175 @@
+//! This module contains the methods for incrementally initializing deferred references to [MaybeUninit] values and slices.
+
+use core::mem::MaybeUninit;
+
+use crate::{Deferred, PointerLength, SliceLike, SlicePointerIndex};
+
+/// # Methods only available for deferred _mutable_ references to [MaybeUninit] values
+impl<'a, T> Deferred<&'a mut MaybeUninit<T>> {
+    /// Initializes the value with `value` and returns a deferred mutable reference to the initialized value.
+    /// This does not create a reference to the uninitialized value. If the value was already initialized,
+    /// the old value is not dropped.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// use core::mem::MaybeUninit;
+    /// let mut value = MaybeUninit::<String>::uninit();
+    /// let mut init = Deferred::from(&mut value).write(String::from("deferred"));
+    /// init.push('!');
+    /// assert_eq!("deferred!", unsafe { value.assume_init() });
+    /// ```
+    pub fn write(self, value: T) -> Deferred<&'a mut T> {
+        let ptr = self.as_mut_ptr() as *mut T;
+        // SAFETY: the pointer is valid for writes and the value is initialized after the write.
+        unsafe {
+            ptr.write(value);
+            Deferred::from_raw_mut(ptr)
+        }
+    }
+
+    /// Converts this deferred reference into a deferred mutable reference to the initialized value.
+    ///
+    /// # Safety
+    /// The caller must guarantee that the value is initialized, see [MaybeUninit::assume_init].
+    pub unsafe fn assume_init(self) -> Deferred<&'a mut T> {
+        Deferred::from_raw_mut(self.as_mut_ptr() as *mut T)
+    }
+}
+
+/// # Methods only available for deferred _mutable_ references to slices and arrays of [MaybeUninit] values
+/// These methods initialize the elements of a deferred uninitialized slice without creating a reference
+/// to the whole slice. Together with [Deferred::split_at_mut], this allows disjoint parts of one
+/// uninitialized buffer to be initialized at the same time, e.g. by different threads.
+///
+/// # Example
+/// ```
+/// use core::mem::MaybeUninit;
+/// use deferred_reference::Deferred;
+/// let mut output = [[MaybeUninit::<u64>::uninit(); 500]; 2];
+/// // note: the halves are arrays (rather than slices), so this works without the `slice_ptr_len` feature, too.
+/// let [left, right] = &mut output;
+/// let (mut left, mut right) = (Deferred::from(left), Deferred::from(right));
+/// std::thread::scope(|scope| {
+///     scope.spawn(move || (0..500).for_each(|i| left.write(i, i as u64)));
+///     scope.spawn(move || (0..500).for_each(|i| right.write(i, 500 + i as u64)));
+/// });
+/// // SAFETY: all 1000 elements were initialized by the threads.
+/// let init = unsafe { Deferred::from(&mut output[1]).assume_init() };
+/// assert_eq!(999, (*init)[499]);
+/// ```
+impl<'a, S, T> Deferred<&'a mut S>
+where
+    S: SliceLike<Element = MaybeUninit<T>> + ?Sized,
+{
+    /// Initializes the element at `index` with `value`, without creating any references.
+    /// If the element was already initialized, the old value is not dropped.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    /// For slices this method also needs the length of the slice. See [Deferred::len] for when this can panic.
+    #[track_caller]
+    pub fn write(&mut self, index: usize, value: T) {
+        let ptr = index.index_mut(self.as_mut_ptr()) as *mut T;
+        // SAFETY: the element lies within the array or slice, so it is valid for writes.
+        unsafe { ptr.write(value) }
+    }
+
+    /// Initializes the elements starting at `offset` with copies of the elements of `src`, without creating
+    /// a reference to any of the other elements. If the elements were already initialized, the old values are not dropped.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// use core::mem::MaybeUninit;
+    /// let mut buffer = [MaybeUninit::<u8>::uninit(); 6];
+    /// let mut deferred = Deferred::from(&mut buffer);
+    /// deferred.write_slice(0, b"def");
+    /// deferred.write_slice(3, b"err");
+    /// assert_eq!(b"deferr", &*unsafe { deferred.assume_init() });
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if the elements do not lie within the array or slice.
+    /// For slices this method also needs the length of the slice. See [Deferred::len] for when this can panic.
+    #[track_caller]
+    pub fn write_slice(&mut self, offset: usize, src: &[T])
+    where
+        T: Copy,
+    {
+        let dst = (offset..offset.saturating_add(src.len())).index_mut(self.as_mut_ptr()) as *mut T;
+        // SAFETY: the elements lie within the array or slice and they can not overlap with `src`,
+        // SAFETY: because `src` can not be borrowed while `self` is mutably borrowed.
+        unsafe { core::ptr::copy_nonoverlapping(src.as_ptr(), dst, src.len()) }
+    }
+
+    /// Converts this deferred reference into a deferred mutable reference to the initialized slice.
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    ///
+    /// # Safety
+    /// The caller must guarantee that all elements are initialized, see [MaybeUninit::assume_init].
+    pub unsafe fn assume_init(self) -> Deferred<&'a mut [T]> {
+        let len = PointerLength::len(self.as_ptr());
+        Deferred::from_raw_parts_mut(self.as_mut_ptr() as *mut T, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::rc::Rc;
+    use core::mem::MaybeUninit;
+    use crate::Deferred;
+
+    #[test]
+    fn write_value() {
+        let rc = Rc::new(());
+        let mut value = MaybeUninit::<Rc<()>>::uninit();
+        let init = Deferred::from(&mut value).write(rc.clone());
+        assert_eq!(2, Rc::strong_count(&init));
+        let init = unsafe { Deferred::from(&mut value).assume_init() };
+        assert_eq!(2, Rc::strong_count(&init));
+        unsafe { value.assume_init_drop() };
+        assert_eq!(1, Rc::strong_count(&rc));
+    }
+
+    #[cfg(feature = "slice_ptr_len")]
+    #[test]
+    fn split_and_write() {
+        let mut buffer = [MaybeUninit::<u16>::uninit(); 8];
+        let mut deferred = Deferred::from(&mut buffer);
+        {
+            let (mut left, mut right) = deferred.split_at_mut(3);
+            right.write_slice(0, &[3, 4, 5, 6, 7]);
+            left.write(2, 2);
+            left.write_slice(0, &[0, 1]);
+        }
+        let init = unsafe { deferred.assume_init() };
+        assert_eq!(&[0, 1, 2, 3, 4, 5, 6, 7], &*init);
+    }
+
+    #[cfg(feature = "slice_ptr_len")]
+    #[test]
+    fn slice() {
+        let mut vec = alloc::vec::Vec::<u32>::with_capacity(4);
+        let mut deferred = Deferred::from(&mut vec.spare_capacity_mut()[..4]);
+        deferred.write_slice(1, &[1, 2, 3]);
+        deferred.write(0, 0);
+        assert_eq!(&[0, 1, 2, 3], &*unsafe { deferred.assume_init() });
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_bounds() {
+        let mut buffer = [MaybeUninit::<u8>::uninit(); 4];
+        Deferred::from(&mut buffer).write_slice(2, &[0; 3]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn write_at_len() {
+        let mut buffer = [MaybeUninit::<u8>::uninit(); 4];
+        Deferred::from(&mut buffer).write(4, 0);
+    }
+}
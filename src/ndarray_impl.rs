@@ -0,0 +1,293 @@
+//! This module contains the conversions between deferred slices and the array views of the `ndarray` crate.
+//! This module is only available with the `ndarray` feature.
+
+use ndarray::{ArrayView, ArrayViewMut, Axis, Dimension, ErrorKind, IntoDimension, ShapeBuilder, ShapeError};
+
+use crate::{Deferred, DeferredStrided, SliceLike};
+
+/// Checks that `shape` has exactly `len` elements.
+fn check_shape<D: Dimension>(shape: &D, len: usize) -> Result<(), ShapeError> {
+    match shape.size_checked() {
+        Some(size) if size == len => Ok(()),
+        Some(_) => Err(ShapeError::from_kind(ErrorKind::IncompatibleShape)),
+        None => Err(ShapeError::from_kind(ErrorKind::Overflow)),
+    }
+}
+
+/// Returns the number of lanes along `axis` for an array with the given `shape`.
+fn lane_count<D: Dimension>(shape: &D, axis: Axis) -> usize {
+    let dims = shape.slice();
+    dims[..axis.index()].iter().chain(&dims[axis.index() + 1..]).product()
+}
+
+/// Computes the offset of the first element and the stride of lane `index` along `axis`
+/// for an array with the given `shape` in row-major order.
+fn lane<D: Dimension>(shape: &D, axis: Axis, index: usize) -> (usize, usize) {
+    let len = shape[axis.index()];
+    let stride: usize = shape.slice()[axis.index() + 1..].iter().product();
+    if len == 0 {
+        // empty lanes do not point to any elements, this avoids pointer arithmetic beyond the slice
+        return (0, stride.max(1));
+    }
+    let outer = index / stride;
+    (outer * len * stride + index % stride, stride)
+}
+
+/// # Methods for converting deferred arrays and slices to and from `ndarray` array views
+/// These methods are only available with the `ndarray` feature. The conversions to array views only create
+/// an array view on the array or slice (which itself is a reference to all of its elements), but they do not
+/// create an intermediate `&[T]` or `&mut [T]` first. The conversions from array views do not create any
+/// references at all.
+impl<'a, S, T> Deferred<&'a S>
+where
+    S: SliceLike<Element = T> + ?Sized,
+{
+    /// Converts the deferred array or slice into an array view with the given shape. The shape can use
+    /// either row-major order (the default) or column-major order (e.g. `(2, 3).f()`).
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let buffer = [1, 2, 3, 4, 5, 6];
+    /// let view = Deferred::new(&buffer).into_array_view((2, 3)).unwrap();
+    /// assert_eq!(6, view[[1, 2]]);
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the number of elements in `shape` differs from the length of the array or slice.
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    pub fn into_array_view<Sh>(self, shape: Sh) -> Result<ArrayView<'a, T, Sh::Dim>, ShapeError>
+    where
+        Sh: ShapeBuilder,
+    {
+        let shape = shape.into_shape();
+        check_shape(shape.raw_dim(), self.len())?;
+        // SAFETY: the shape covers exactly the elements of the array or slice, which are valid for lifetime `'a`.
+        Ok(unsafe { ArrayView::from_shape_ptr(shape, self.as_ptr() as *const T) })
+    }
+}
+
+impl<'a, T> Deferred<&'a [T]> {
+    /// Converts an array view back into a deferred slice, without creating any references.
+    /// Returns `None` if the elements of `view` are not contiguous in row-major order.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let array = ndarray::arr2(&[[1, 2, 3], [4, 5, 6]]);
+    /// let deferred = Deferred::from_array_view(array.view()).unwrap();
+    /// assert_eq!([1, 2, 3, 4, 5, 6], *deferred);
+    /// assert!(Deferred::from_array_view(array.t()).is_none());
+    /// ```
+    pub fn from_array_view<D: Dimension>(view: ArrayView<'a, T, D>) -> Option<Self> {
+        if view.is_standard_layout() {
+            // SAFETY: the elements of the view are contiguous and valid for lifetime `'a`.
+            Some(unsafe { Deferred::from_raw_parts(view.as_ptr(), view.len()) })
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a, S, T> Deferred<&'a mut S>
+where
+    S: SliceLike<Element = T> + ?Sized,
+{
+    /// Converts the deferred array or slice into a mutable array view with the given shape. The shape can use
+    /// either row-major order (the default) or column-major order (e.g. `(2, 3).f()`).
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// use ndarray::ShapeBuilder;
+    /// let mut buffer = [0; 6];
+    /// let mut view = Deferred::from(&mut buffer).into_array_view_mut((2, 3).f()).unwrap();
+    /// view[[1, 0]] = 1;
+    /// assert_eq!([0, 1, 0, 0, 0, 0], buffer);
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the number of elements in `shape` differs from the length of the array or slice.
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    pub fn into_array_view_mut<Sh>(self, shape: Sh) -> Result<ArrayViewMut<'a, T, Sh::Dim>, ShapeError>
+    where
+        Sh: ShapeBuilder,
+    {
+        let shape = shape.into_shape();
+        check_shape(shape.raw_dim(), self.len())?;
+        // SAFETY: the shape covers exactly the elements of the array or slice, which are valid for lifetime `'a`.
+        // SAFETY: the elements do not overlap, because the shape uses either row-major or column-major order.
+        Ok(unsafe { ArrayViewMut::from_shape_ptr(shape, self.as_mut_ptr() as *mut T) })
+    }
+}
+
+impl<'a, T> Deferred<&'a mut [T]> {
+    /// Converts a mutable array view back into a deferred mutable slice, without creating any references.
+    /// Returns `None` if the elements of `view` are not contiguous in row-major order.
+    pub fn from_array_view_mut<D: Dimension>(mut view: ArrayViewMut<'a, T, D>) -> Option<Self> {
+        if view.is_standard_layout() {
+            // SAFETY: the elements of the view are contiguous and valid for lifetime `'a`.
+            Some(unsafe { Deferred::from_raw_parts_mut(view.as_mut_ptr(), view.len()) })
+        } else {
+            None
+        }
+    }
+}
+
+/// # Methods for splitting deferred arrays and slices into `ndarray` lanes
+impl<'a, S, T> Deferred<&'a S>
+where
+    S: SliceLike<Element = T> + ?Sized,
+    T: 'a,
+{
+    /// Interprets the deferred array or slice as an array of the given shape in row-major order and returns
+    /// an iterator over its lanes along `axis`. A lane is the one-dimensional view on the elements
+    /// for which only the index along `axis` varies. For example, the lanes of a matrix along
+    /// `Axis(0)` are its columns and the lanes along `Axis(1)` are its rows. The lanes are disjoint.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// use ndarray::Axis;
+    /// let buffer = [1, 2, 3, 4, 5, 6];
+    /// let columns: Vec<Vec<i32>> = Deferred::new(&buffer).lanes((2, 3), Axis(0)).unwrap()
+    ///     .map(|column| column.iter().copied().collect())
+    ///     .collect();
+    /// assert_eq!(vec![vec![1, 4], vec![2, 5], vec![3, 6]], columns);
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the number of elements in `shape` differs from the length of the array or slice.
+    ///
+    /// # Panics
+    /// Panics if `axis` is out of bounds for `shape`.
+    /// For slices this method also needs the length of the slice. See [Deferred::len] for when this can panic.
+    pub fn lanes<Sh>(
+        self,
+        shape: Sh,
+        axis: Axis,
+    ) -> Result<impl DoubleEndedIterator<Item = DeferredStrided<&'a T>> + ExactSizeIterator, ShapeError>
+    where
+        Sh: IntoDimension,
+    {
+        let shape = shape.into_dimension();
+        check_shape(&shape, self.len())?;
+        let (len, count) = (shape[axis.index()], lane_count(&shape, axis));
+        let ptr = self.as_ptr() as *const T;
+        Ok((0..count).map(move |index| {
+            let (offset, stride) = lane(&shape, axis, index);
+            // SAFETY: the lanes lie within the array or slice, which is valid for lifetime `'a`.
+            unsafe { DeferredStrided::from_raw_parts(core::ptr::NonNull::new_unchecked(ptr.add(offset) as *mut T), len, stride) }
+        }))
+    }
+}
+
+impl<'a, S, T> Deferred<&'a mut S>
+where
+    S: SliceLike<Element = T> + ?Sized,
+    T: 'a,
+{
+    /// Interprets the deferred array or slice as an array of the given shape in row-major order and returns
+    /// an iterator over its disjoint mutable lanes along `axis`. See [Deferred::lanes] for more details.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// use ndarray::Axis;
+    /// let mut buffer = [0usize; 6];
+    /// let deferred = Deferred::from(&mut buffer);
+    /// std::thread::scope(|scope| {
+    ///     for (i, mut column) in deferred.lanes_mut((2, 3), Axis(0)).unwrap().enumerate() {
+    ///         scope.spawn(move || column.iter_mut().for_each(|element| *element = i));
+    ///     }
+    /// });
+    /// assert_eq!([0, 1, 2, 0, 1, 2], buffer);
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the number of elements in `shape` differs from the length of the array or slice.
+    ///
+    /// # Panics
+    /// Panics if `axis` is out of bounds for `shape`.
+    /// For slices this method also needs the length of the slice. See [Deferred::len] for when this can panic.
+    pub fn lanes_mut<Sh>(
+        self,
+        shape: Sh,
+        axis: Axis,
+    ) -> Result<impl DoubleEndedIterator<Item = DeferredStrided<&'a mut T>> + ExactSizeIterator, ShapeError>
+    where
+        Sh: IntoDimension,
+    {
+        let shape = shape.into_dimension();
+        check_shape(&shape, self.len())?;
+        let (len, count) = (shape[axis.index()], lane_count(&shape, axis));
+        let ptr = self.as_mut_ptr() as *mut T;
+        Ok((0..count).map(move |index| {
+            let (offset, stride) = lane(&shape, axis, index);
+            // SAFETY: the lanes lie within the array or slice, which is valid for lifetime `'a`.
+            // SAFETY: every lane is handed out only once and the lanes do not overlap.
+            unsafe { DeferredStrided::from_raw_parts(core::ptr::NonNull::new_unchecked(ptr.add(offset)), len, stride) }
+        }))
+    }
+}
+
+// note: all of these tests need the length of deferred slices.
+#[cfg(all(test, feature = "slice_ptr_len"))]
+mod tests {
+    use alloc::vec::Vec;
+    use core::cell::UnsafeCell;
+    use ndarray::{Axis, ShapeBuilder};
+    use crate::{DeferMut, Deferred};
+
+    #[test]
+    fn round_trip() {
+        let buffer = UnsafeCell::new([0u32; 24]);
+        let deferred: Deferred<&mut [u32]> = unsafe { buffer.defer_mut() }.into();
+        let ptr = deferred.as_ptr();
+        let mut view = deferred.into_array_view_mut((2, 3, 4)).unwrap();
+        view[[1, 2, 3]] = 1;
+        let deferred = Deferred::from_array_view_mut(view).unwrap();
+        assert_eq!(ptr, deferred.as_ptr());
+        let view = deferred.into_ref().into_array_view((6, 4)).unwrap();
+        assert_eq!(1, view[[5, 3]]);
+        assert_eq!(24, Deferred::from_array_view(view).unwrap().len());
+    }
+
+    #[test]
+    fn incompatible_shapes() {
+        let buffer = [0u8; 6];
+        let deferred: Deferred<&[u8]> = Deferred::new(&buffer).into();
+        assert!(deferred.into_array_view((2, 2)).is_err());
+        assert!(deferred.into_array_view((usize::MAX, 2)).is_err());
+        assert!(deferred.lanes((4, 2), Axis(0)).is_err());
+        let view = deferred.into_array_view((2, 3).f()).unwrap();
+        assert!(Deferred::from_array_view(view).is_none());
+    }
+
+    #[test]
+    fn lanes() {
+        let buffer = UnsafeCell::new([0usize; 24]);
+        let deferred: Deferred<&mut [usize]> = unsafe { buffer.defer_mut() }.into();
+        // all lanes along the middle axis are alive at the same time
+        let mut lanes: Vec<_> = deferred.lanes_mut((2, 3, 4), Axis(1)).unwrap().collect();
+        assert_eq!(8, lanes.len());
+        for (i, lane) in lanes.iter_mut().enumerate() {
+            assert_eq!((3, 4), (lane.len(), lane.stride()));
+            lane[1] = i;
+        }
+        let array = ndarray::Array::from_iter(unsafe { *buffer.get() }.iter().copied()).into_shape((2, 3, 4)).unwrap();
+        let expected = ndarray::Array::from_iter(0..8).into_shape((2, 4)).unwrap();
+        assert_eq!(expected, array.index_axis(Axis(1), 1));
+        let deferred: Deferred<&[usize]> = unsafe { buffer.defer_mut() }.into_ref().into();
+        let rows: Vec<Vec<usize>> = deferred.lanes((6, 4), Axis(1)).unwrap().map(|row| row.iter().copied().collect()).collect();
+        assert_eq!(alloc::vec![0, 1, 2, 3], rows[1]);
+        let empty: Deferred<&[usize]> = Deferred::new(&[][..]);
+        let lanes: Vec<_> = empty.lanes((0, 3), Axis(0)).unwrap().collect();
+        assert_eq!(3, lanes.len());
+        assert!(lanes.iter().all(|lane| lane.is_empty()));
+    }
+}
@@ -0,0 +1,186 @@
+//! This module contains the methods for pinned deferred mutable references (i.e. `Pin<Deferred<&mut T>>`).
+
+use core::pin::Pin;
+
+use crate::{Deferred, SliceLike, SlicePointerIndex};
+
+/// Returns the pointer of a pinned deferred reference, without dereferencing it.
+fn pinned_ptr<T: ?Sized>(pin: &Pin<Deferred<&mut T>>) -> *mut T {
+    // SAFETY: `Pin` is `#[repr(transparent)]`, so a reference to a `Pin<P>` is also a valid reference to `P`.
+    // SAFETY: the `Deferred` is only used to read its pointer, which does not move the pointee.
+    unsafe { &*(pin as *const Pin<Deferred<&mut T>> as *const Deferred<&mut T>) }.as_mut_ptr()
+}
+
+/// # Methods for pinned deferred mutable references
+/// A `Pin<Deferred<&mut T>>` behaves just like a `Pin<&mut T>`, except that no reference to the pointee is
+/// created until it is dereferenced with [Pin::as_mut] or [Pin::as_ref]. The pinned pointee can never be moved
+/// through a pinned deferred reference from safe code. The associated functions in this block offer structural
+/// pin projection to the elements of arrays and slices ([Deferred::pin_index_mut] and [Deferred::pin_split_at_mut])
+/// and to fields of structs ([Deferred::pin_map_unchecked_mut]) without dereferencing the pinned deferred reference.
+///
+/// # Example
+/// ```
+/// use deferred_reference::Deferred;
+/// use core::marker::PhantomPinned;
+/// use core::pin::Pin;
+/// struct Task {
+///     state: u32,
+///     _pinned: PhantomPinned,
+/// }
+/// fn task() -> Task {
+///     Task { state: 0, _pinned: PhantomPinned }
+/// }
+/// let mut slab = Box::pin([task(), task(), task(), task()]);
+/// let mut tasks = Deferred::new_pinned(slab.as_mut());
+/// // note: projecting into an array (rather than into a slice, e.g. after `Deferred::pin_split_at_mut`)
+/// // works without the `slice_ptr_len` feature, too.
+/// // SAFETY: `state` is not structurally pinned, so it may be accessed by mutable reference.
+/// let mut state = unsafe {
+///     Deferred::pin_map_unchecked_mut(Deferred::pin_index_mut(&mut tasks, 3), |task| core::ptr::addr_of_mut!((*task).state))
+/// };
+/// *state.as_mut() = 3;
+/// // `Pin::as_mut` dereferences the pinned deferred reference into a `Pin<&mut T>`:
+/// unsafe { Pin::get_unchecked_mut(Deferred::pin_index_mut(&mut tasks, 0).as_mut()) }.state = 1;
+/// assert_eq!([1, 0, 0, 3], [slab[0].state, slab[1].state, slab[2].state, slab[3].state]);
+/// ```
+impl<'a, T: ?Sized> Deferred<&'a mut T> {
+    /// Converts a pinned mutable reference into a pinned deferred mutable reference.
+    pub fn new_pinned(pin: Pin<&'a mut T>) -> Pin<Self> {
+        // SAFETY: the pointee stays pinned, because `Deferred` only gives out references to the same pointee.
+        unsafe { Pin::new_unchecked(Deferred::from(Pin::into_inner_unchecked(pin))) }
+    }
+
+    /// Dereferences this deferred mutable reference into a pinned mutable reference.
+    ///
+    /// # Safety
+    /// The caller must guarantee that the pointee is pinned, i.e. that it is never moved again until it is dropped.
+    /// See the [pin module](core::pin) for more information. The same rules as for [DerefMut](core::ops::DerefMut)
+    /// apply to the mutable reference inside the returned [Pin].
+    pub unsafe fn as_pin_mut(&mut self) -> Pin<&mut T> {
+        Pin::new_unchecked(&mut *self.as_mut_ptr())
+    }
+
+    /// Makes a copy of a pinned deferred mutable reference, without dereferencing it.
+    ///
+    /// # Safety
+    /// The same rules apply as for [Deferred::clone_unchecked].
+    pub unsafe fn pin_clone_unchecked(pin: &Pin<Self>) -> Pin<Self> {
+        Pin::new_unchecked(Deferred::from_raw_mut(pinned_ptr(pin)))
+    }
+
+    /// Projects a pinned deferred mutable reference onto a field (or any other part) of the pinned pointee,
+    /// without dereferencing it. `project` receives a pointer to the pointee and must return a pointer to the field,
+    /// e.g. with [addr_of_mut!](core::ptr::addr_of_mut).
+    ///
+    /// # Safety
+    /// The same rules apply as for [Pin::map_unchecked_mut]. In addition, `project` must return a pointer
+    /// to a place within the pointee and it must not create any references to the pointee.
+    pub unsafe fn pin_map_unchecked_mut<U, F>(pin: Pin<Self>, project: F) -> Pin<Deferred<&'a mut U>>
+    where
+        U: ?Sized,
+        F: FnOnce(*mut T) -> *mut U,
+    {
+        Pin::new_unchecked(Deferred::from_raw_mut(project(pinned_ptr(&pin))))
+    }
+}
+
+/// # Methods for pinned deferred mutable references to slices and arrays
+/// The elements of a pinned array or slice are structurally pinned, so these projections are safe.
+impl<T> Deferred<&mut T>
+where
+    T: SliceLike + ?Sized,
+{
+    /// Projects a pinned deferred mutable reference to an array or slice onto the element at `index`,
+    /// without dereferencing it.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    /// For slices this method also needs the length of the slice. See [Deferred::len] for when this can panic.
+    #[track_caller]
+    pub fn pin_index_mut(pin: &mut Pin<Self>, index: usize) -> Pin<Deferred<&mut T::Element>> {
+        let ptr = index.index_mut(pinned_ptr(pin));
+        // SAFETY: the element lies within the pinned array or slice, which is mutably borrowed.
+        unsafe { Pin::new_unchecked(Deferred::from_raw_mut(ptr)) }
+    }
+
+    /// Splits a pinned deferred mutable reference to an array or slice into two disjoint pinned deferred mutable
+    /// references at index `mid`, without dereferencing it.
+    ///
+    /// # Panics
+    /// Panics if `mid > len`.
+    /// For slices this method also needs the length of the slice. See [Deferred::len] for when this can panic.
+    #[track_caller]
+    #[allow(clippy::type_complexity)]
+    pub fn pin_split_at_mut(pin: &mut Pin<Self>, mid: usize) -> (Pin<Deferred<&mut [T::Element]>>, Pin<Deferred<&mut [T::Element]>>) {
+        let ptr = pinned_ptr(pin);
+        let left = (..mid).index_mut(ptr);
+        // SAFETY: `..mid` lies within the array or slice, so `mid..` can not panic anymore.
+        // SAFETY: both halves are disjoint and they lie within the pinned array or slice, which is mutably borrowed.
+        unsafe {
+            (
+                Pin::new_unchecked(Deferred::from_raw_mut(left)),
+                Pin::new_unchecked(Deferred::from_raw_mut((mid..).index_mut(ptr))),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::marker::PhantomPinned;
+    use core::pin::Pin;
+    use crate::Deferred;
+
+    /// A self-referential struct, which stores a pointer to its own `value`.
+    struct SelfRef {
+        value: u32,
+        ptr: *const u32,
+        _pinned: PhantomPinned,
+    }
+
+    impl SelfRef {
+        /// Initializes the pointer to `value`.
+        fn init(self: Pin<&mut Self>) {
+            let this = unsafe { self.get_unchecked_mut() };
+            this.ptr = &this.value;
+        }
+    }
+
+    #[test]
+    fn self_referential() {
+        let mut slab = [(); 3].map(|_| SelfRef { value: 0, ptr: core::ptr::null(), _pinned: PhantomPinned });
+        let mut pin = Deferred::new_pinned(unsafe { Pin::new_unchecked(&mut slab) });
+        for index in 0..3 {
+            let mut element = Deferred::pin_index_mut(&mut pin, index);
+            element.as_mut().init();
+            let mut value = unsafe {
+                Deferred::pin_map_unchecked_mut(element, |this| core::ptr::addr_of_mut!((*this).value))
+            };
+            *value.as_mut() = index as u32 + 1;
+        }
+        let mut other = unsafe { Deferred::pin_clone_unchecked(&pin) };
+        let element = Deferred::pin_index_mut(&mut other, 2);
+        assert_eq!(3, unsafe { *element.as_ref().get_ref().ptr });
+        assert!(slab.iter().all(|this| core::ptr::eq(this.ptr, &this.value)));
+    }
+
+    #[test]
+    fn split() {
+        let mut buffer = [1u8, 2, 3];
+        let mut pin = Deferred::new_pinned(Pin::new(&mut buffer));
+        let (left, mut right) = Deferred::pin_split_at_mut(&mut pin, 1);
+        right.as_mut()[0] = 4;
+        assert_eq!(&[1], &*left);
+        let mut deferred = Deferred::from(&mut buffer);
+        assert_eq!(&4, unsafe { deferred.as_pin_mut() }.get_mut().get(1).unwrap());
+        assert_eq!([1, 4, 3], buffer);
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_bounds() {
+        let mut buffer = [0u8; 4];
+        let mut pin = Deferred::new_pinned(Pin::new(&mut buffer));
+        Deferred::pin_index_mut(&mut pin, 4);
+    }
+}
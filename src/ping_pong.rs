@@ -0,0 +1,165 @@
+//! This module contains the [PingPong] double buffer.
+
+use crate::Deferred;
+
+/// A double buffer which keeps a front buffer and a back buffer in the two halves of one array or slice.
+/// The front buffer is handed out as a `Deferred<&[T]>` for reading and the back buffer is handed out as a
+/// `Deferred<&mut [T]>` for writing. The halves are disjoint, so the front buffer can be read (e.g. by other
+/// threads) while the back buffer is being written, without ever aliasing the writer.
+/// After the back buffer has been written, [PingPong::swap] flips the two buffers.
+///
+/// # Example
+/// ```
+/// use deferred_reference::PingPong;
+/// let mut buffer = [0u32; 8];
+/// let mut ping_pong = PingPong::from(&mut buffer[..]);
+/// for step in 1..=3 {
+///     let (front, mut back) = ping_pong.split();
+///     std::thread::scope(|scope| {
+///         // the readers only see the previous step...
+///         scope.spawn(|| assert!(front.iter().all(|&value| value == step - 1)));
+///         // dereferencing before calling `len` makes this work without the `slice_ptr_len` feature, too
+///         scope.spawn(|| assert_eq!(4, (*front).len()));
+///         // ...while the next step is being written
+///         back.fill(step);
+///     });
+///     ping_pong.swap();
+/// }
+/// assert_eq!([3, 3, 3, 3], *ping_pong.front());
+/// ```
+pub struct PingPong<'a, T> {
+    /// The backing memory of both buffers, of which the first half is the first buffer
+    /// and the second half is the second buffer.
+    memory: Deferred<&'a mut [T]>,
+    /// The number of elements in each buffer.
+    half: usize,
+    /// Whether the front buffer is the second half of `memory` (instead of the first half).
+    swapped: bool,
+}
+
+impl<'a, T> PingPong<'a, T> {
+    /// Creates a new double buffer over `memory`. The front buffer is the first half of `memory` and
+    /// the back buffer is the second half of `memory`. If the length of `memory` is odd, then the last
+    /// element is not used by either buffer.
+    ///
+    /// # Panics
+    /// This method needs the length of the slice. See [Deferred::len] for when this can panic.
+    /// Use the [From] implementation on `&mut [T]` to construct a double buffer on stable Rust.
+    pub fn new(memory: Deferred<&'a mut [T]>) -> Self {
+        let half = memory.len() / 2;
+        // SAFETY: this only shortens the slice by at most one element.
+        let memory = unsafe { Deferred::from_raw_parts_mut(memory.as_mut_ptr() as *mut T, 2 * half) };
+        Self {
+            memory,
+            half,
+            swapped: false,
+        }
+    }
+
+    /// Returns the number of elements in each of the two buffers.
+    pub fn len(&self) -> usize {
+        self.half
+    }
+
+    /// Returns `true` if the buffers are empty.
+    pub fn is_empty(&self) -> bool {
+        self.half == 0
+    }
+
+    /// Returns a raw pointer to the first element of the first half (if `second` is `false`)
+    /// or of the second half (if `second` is `true`) of the backing memory.
+    fn half_ptr(&self, second: bool) -> *mut T {
+        let ptr = self.memory.as_mut_ptr() as *mut T;
+        // SAFETY: the second half starts within (or right at the end of) the backing memory.
+        if second { unsafe { ptr.add(self.half) } } else { ptr }
+    }
+
+    /// Returns a deferred immutable reference to the front buffer.
+    pub fn front(&self) -> Deferred<&[T]> {
+        // SAFETY: the front buffer lies within the backing memory and it borrows `self`.
+        unsafe { Deferred::from_raw_parts(self.half_ptr(self.swapped), self.half) }
+    }
+
+    /// Returns a deferred mutable reference to the back buffer.
+    pub fn back(&mut self) -> Deferred<&mut [T]> {
+        self.split().1
+    }
+
+    /// Returns a deferred immutable reference to the front buffer and a deferred mutable reference
+    /// to the back buffer at the same time. These never overlap.
+    pub fn split(&mut self) -> (Deferred<&[T]>, Deferred<&mut [T]>) {
+        // note: this does not use `Deferred::split_at_mut`, which needs the length of the slice on stable Rust.
+        // SAFETY: the buffers are disjoint halves of the backing memory and they both borrow `self` mutably.
+        unsafe {
+            (
+                Deferred::from_raw_parts(self.half_ptr(self.swapped), self.half),
+                Deferred::from_raw_parts_mut(self.half_ptr(!self.swapped), self.half),
+            )
+        }
+    }
+
+    /// Swaps the front buffer and the back buffer. This requires a mutable reference,
+    /// so that none of the previously handed out buffers can still be in use.
+    pub fn swap(&mut self) {
+        self.swapped = !self.swapped;
+    }
+}
+
+impl<'a, T> From<&'a mut [T]> for PingPong<'a, T> {
+    fn from(slice: &'a mut [T]) -> Self {
+        let half = slice.len() / 2;
+        Self {
+            memory: Deferred::from(&mut slice[..2 * half]),
+            half,
+            swapped: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PingPong;
+
+    #[test]
+    fn swap() {
+        let mut buffer = [0u8; 5];
+        let mut ping_pong = PingPong::from(&mut buffer[..]);
+        assert_eq!(2, ping_pong.len());
+        ping_pong.back().copy_from_slice(&[1, 2]);
+        assert_eq!([0, 0], *ping_pong.front());
+        ping_pong.swap();
+        assert_eq!([1, 2], *ping_pong.front());
+        let (front, mut back) = ping_pong.split();
+        // both buffers can be dereferenced at the same time
+        let (front_ref, back_ref) = (&*front, &mut *back);
+        back_ref.copy_from_slice(front_ref);
+        back_ref[0] = 3;
+        ping_pong.swap();
+        assert_eq!([3, 2], *ping_pong.front());
+        assert_eq!([3, 2, 1, 2, 0], buffer);
+    }
+
+    #[cfg(feature = "slice_ptr_len")]
+    #[test]
+    fn new() {
+        use core::cell::UnsafeCell;
+        use crate::DeferMut;
+        let buffer = UnsafeCell::new([0u8; 5]);
+        let mut ping_pong = PingPong::new(unsafe { buffer.defer_mut() }.into());
+        assert_eq!(2, ping_pong.len());
+        ping_pong.back().copy_from_slice(&[1, 2]);
+        ping_pong.swap();
+        assert_eq!([1, 2], *ping_pong.front());
+        assert_eq!([0, 0, 1, 2, 0], unsafe { *buffer.get() });
+    }
+
+    #[test]
+    fn empty() {
+        let mut buffer = [0u8; 1];
+        let mut ping_pong = PingPong::from(&mut buffer[..]);
+        assert!(ping_pong.is_empty());
+        // note: dereferencing first makes this work without the `slice_ptr_len` feature, too.
+        assert!((*ping_pong.back()).is_empty());
+        assert!((*ping_pong.front()).is_empty());
+    }
+}
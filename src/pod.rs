@@ -0,0 +1,244 @@
+//! This module contains the [Pod] marker trait and the methods for reinterpreting deferred slices as other element types.
+
+use crate::{Deferred, Reference, SliceLike};
+
+/// A marker trait for "plain old data" types, which can be safely reinterpreted as bytes and from bytes.
+/// This trait is used by [Deferred::cast_slice], [Deferred::cast_slice_mut], [Deferred::as_bytes] and
+/// [Deferred::as_bytes_mut]. It is implemented for all primitive integer and floating point types
+/// and for arrays of types which implement [Pod].
+///
+/// # Safety
+/// This trait is unsafe. The implementor must promise that:
+/// * every bit pattern is a valid value of the type (this rules out e.g. `bool`, `char` and references).
+/// * the type does not contain any padding bytes.
+/// * the type does not contain any interior mutability (e.g. an [UnsafeCell](core::cell::UnsafeCell)).
+pub unsafe trait Pod: Copy + 'static {}
+
+/// Implements [Pod] for the given primitive types.
+macro_rules! impl_pod {
+    ($($ty:ty),*) => {
+        $(
+            // SAFETY: primitive integers and floats have no padding and every bit pattern is valid.
+            unsafe impl Pod for $ty {}
+        )*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+// SAFETY: arrays have no padding between their elements, so this inherits the guarantees from `T`.
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// The error returned by [Deferred::cast_slice] and [Deferred::cast_slice_mut]
+/// when a slice can not be reinterpreted as a slice of another element type.
+///
+/// # Example
+/// ```
+/// use core::convert::TryFrom;
+/// use deferred_reference::{CastError, Deferred};
+/// /// An array which is aligned for `u64`.
+/// #[repr(align(8))]
+/// struct Aligned([u32; 3]);
+/// let buffer = Aligned([0; 3]);
+/// assert_eq!(Some(CastError::SizeMismatch), Deferred::new(&buffer.0).cast_slice::<u64>().err());
+/// // note: casting arrays (rather than slices) works without the `slice_ptr_len` feature, too.
+/// let tail = <&[u32; 2]>::try_from(&buffer.0[1..]).unwrap();
+/// assert_eq!(Some(CastError::Unaligned), Deferred::new(tail).cast_slice::<u64>().err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CastError {
+    /// The slice is not properly aligned for the new element type.
+    Unaligned,
+    /// The size of the slice in bytes is not a multiple of the size of the new element type.
+    SizeMismatch,
+}
+
+impl core::fmt::Display for CastError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CastError::Unaligned => f.write_str("slice must be aligned for the new element type"),
+            CastError::SizeMismatch => f.write_str("size of the slice must be a multiple of the size of the new element type"),
+        }
+    }
+}
+
+/// Computes the length of a slice of `len` elements of type `T` when it is reinterpreted as a slice
+/// of elements of type `U` starting at address `ptr`.
+fn cast_len<T, U>(ptr: *const T, len: usize) -> Result<usize, CastError> {
+    // note: the invariant of `Deferred` guarantees that this does not overflow.
+    let size = len * core::mem::size_of::<T>();
+    if ptr as usize & (core::mem::align_of::<U>() - 1) != 0 {
+        Err(CastError::Unaligned)
+    } else if core::mem::size_of::<U>() == 0 {
+        if size == 0 { Ok(0) } else { Err(CastError::SizeMismatch) }
+    } else {
+        let len = size / core::mem::size_of::<U>();
+        if len * core::mem::size_of::<U>() == size { Ok(len) } else { Err(CastError::SizeMismatch) }
+    }
+}
+
+/// # Methods for reinterpreting deferred references to slices and arrays of [Pod] types
+/// These methods reinterpret the elements of an array or slice as another [Pod] type, without
+/// dereferencing the array or slice. The length of the new slice is computed from the length
+/// of the array or slice.
+impl<T> Deferred<T>
+where
+    T: Reference,
+    T::Target: SliceLike,
+    <T::Target as SliceLike>::Element: Pod,
+{
+    /// Reinterprets the array or slice as a slice of elements of type `U`.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let header = [0x01u8, 0x00, 0x02, 0x00];
+    /// let deferred = Deferred::new(&header);
+    /// match deferred.cast_slice::<u16>() {
+    ///     // dereferencing before indexing makes this work without the `slice_ptr_len` feature, too
+    ///     Ok(fields) => assert_eq!(u16::from_ne_bytes([0x02, 0x00]), (*fields)[1]),
+    ///     Err(error) => println!("packet is not aligned: {}", error),
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the array or slice is not aligned for `U`, or if its size in bytes
+    /// is not a multiple of the size of `U`.
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    pub fn cast_slice<U: Pod>(&self) -> Result<Deferred<&[U]>, CastError> {
+        let ptr = self.as_ptr() as *const <T::Target as SliceLike>::Element;
+        let len = cast_len::<_, U>(ptr, self.len())?;
+        // SAFETY: the new slice is aligned and it covers exactly the same bytes as `self`.
+        // SAFETY: both element types are `Pod`, so every byte of `self` is a valid byte of `U`.
+        Ok(unsafe { Deferred::from_raw_parts(ptr as *const U, len) })
+    }
+
+    /// Reinterprets the array or slice as a slice of bytes.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let buffer = [1u16, 2, 3];
+    /// let deferred = Deferred::new(&buffer);
+    /// // dereferencing the bytes first makes this work without the `slice_ptr_len` feature, too
+    /// let bytes = &*deferred.as_bytes();
+    /// assert_eq!(6, bytes.len());
+    /// assert_eq!(2u16.to_ne_bytes(), bytes[2..4]);
+    /// ```
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    pub fn as_bytes(&self) -> Deferred<&[u8]> {
+        match self.cast_slice() {
+            Ok(bytes) => bytes,
+            // bytes are always aligned and every size is a multiple of the size of a byte
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+/// # Methods for reinterpreting deferred _mutable_ references to slices and arrays of [Pod] types
+impl<T> Deferred<&mut T>
+where
+    T: SliceLike + ?Sized,
+    T::Element: Pod,
+{
+    /// Reinterprets the array or slice as a mutable slice of elements of type `U`.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let mut upload = [[0u32; 2]; 2];
+    /// let mut deferred = Deferred::from(&mut upload);
+    /// // note: casting arrays (rather than slices) works without the `slice_ptr_len` feature, too.
+    /// let mut tail = Deferred::from(&mut deferred[1]);
+    /// tail.cast_slice_mut::<u16>().unwrap().copy_from_slice(&[1, 2, 3, 4]);
+    /// assert_eq!(*Deferred::new(&[1u16, 2, 3, 4]).as_bytes(), *Deferred::new(&upload[1]).as_bytes());
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the array or slice is not aligned for `U`, or if its size in bytes
+    /// is not a multiple of the size of `U`.
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    pub fn cast_slice_mut<U: Pod>(&mut self) -> Result<Deferred<&mut [U]>, CastError> {
+        let ptr = self.as_mut_ptr() as *mut T::Element;
+        let len = cast_len::<_, U>(ptr, self.len())?;
+        // SAFETY: the new slice is aligned and it covers exactly the same bytes as `self`.
+        // SAFETY: both element types are `Pod`, so any bytes written through the new slice are valid for `T::Element`.
+        Ok(unsafe { Deferred::from_raw_parts_mut(ptr as *mut U, len) })
+    }
+
+    /// Reinterprets the array or slice as a mutable slice of bytes.
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    pub fn as_bytes_mut(&mut self) -> Deferred<&mut [u8]> {
+        match self.cast_slice_mut() {
+            Ok(bytes) => bytes,
+            // bytes are always aligned and every size is a multiple of the size of a byte
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+// note: all of these tests need the length of deferred slices.
+#[cfg(all(test, feature = "slice_ptr_len"))]
+mod tests {
+    use core::cell::UnsafeCell;
+    use core::ops::{Deref, DerefMut};
+    use crate::{CastError, DeferMut, Deferred};
+
+    #[test]
+    fn cast_array() {
+        let buffer = [[1u8, 2], [3, 4], [5, 6]];
+        let deferred = Deferred::new(&buffer);
+        let bytes = deferred.as_bytes();
+        assert_eq!(&[1, 2, 3, 4, 5, 6], bytes.deref());
+        let pairs = bytes.cast_slice::<[u8; 2]>().unwrap();
+        assert_eq!(&buffer, pairs.deref());
+        let triples = bytes.cast_slice::<[u8; 3]>().unwrap();
+        assert_eq!(&[[1, 2, 3], [4, 5, 6]], triples.deref());
+        assert_eq!(Some(CastError::SizeMismatch), bytes.cast_slice::<[u8; 4]>().err());
+    }
+
+    #[test]
+    fn zero_sized() {
+        let buffer = [0u8; 4];
+        let deferred = Deferred::new(&buffer);
+        assert_eq!(Some(CastError::SizeMismatch), deferred.cast_slice::<[u8; 0]>().err());
+        let empty = Deferred::new(&[0u64; 0]);
+        assert!(empty.cast_slice::<[u64; 0]>().unwrap().deref().is_empty());
+    }
+
+    #[test]
+    fn unaligned() {
+        let buffer = [0u64; 2];
+        let deferred = Deferred::new(&buffer);
+        let bytes = deferred.as_bytes();
+        let unaligned = unsafe { Deferred::from_raw_parts(bytes.as_ptr().cast::<u8>().add(2), 8) };
+        assert_eq!(Some(CastError::Unaligned), unaligned.cast_slice::<u32>().err());
+        assert_eq!(4, unaligned.cast_slice::<u16>().unwrap().deref().len());
+    }
+
+    #[test]
+    fn disjoint_casts() {
+        let buffer = UnsafeCell::new([0u32; 4]);
+        let mut deferred = unsafe { buffer.defer_mut() };
+        let mut left = unsafe { deferred.clone_unchecked() };
+        let bytes = deferred.as_bytes_mut();
+        let mut right = unsafe { Deferred::from_raw_parts_mut(bytes.as_mut_ptr().cast::<u8>().add(8), 8) };
+        // the bytes of the right half and the first element are mutated at the same time
+        let right_ref = right.deref_mut();
+        let left_ref = &mut left[0];
+        right_ref.copy_from_slice(&[0xff; 8]);
+        *left_ref = 1;
+        assert_eq!([1, 0, u32::MAX, u32::MAX], unsafe { *buffer.get() });
+        let mut halves = right.cast_slice_mut::<u16>().unwrap();
+        halves.deref_mut()[0] = 0;
+        assert_eq!(u32::from_ne_bytes([0, 0, 0xff, 0xff]), unsafe { *buffer.get() }[2]);
+    }
+}
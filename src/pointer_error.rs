@@ -0,0 +1,87 @@
+//! This module contains the error type returned by the checked constructors of [Deferred](crate::Deferred).
+
+use crate::{PointerLayout, slice_size};
+
+/// The error returned by the checked constructors [Deferred::from_raw_checked](crate::Deferred::from_raw_checked)
+/// and [Deferred::from_raw_mut_checked](crate::Deferred::from_raw_mut_checked) when a pointer does not
+/// uphold the invariant of [Deferred](crate::Deferred).
+///
+/// # Example
+/// ```
+/// use deferred_reference::{Deferred, PointerError};
+/// let result = unsafe { Deferred::from_raw_checked(core::ptr::null::<u32>()) };
+/// assert_eq!(Some(PointerError::Null), result.err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PointerError {
+    /// The pointer is null.
+    Null,
+    /// The pointer is not properly aligned for the type that it points to.
+    Unaligned,
+    /// The size of the pointee exceeds `isize::MAX` bytes.
+    TooLarge,
+}
+
+impl PointerError {
+    /// Checks that `ptr` is non-null, that it is properly aligned and that the size of its pointee
+    /// does not exceed `isize::MAX` bytes, without dereferencing the pointer.
+    pub(crate) fn check<T>(ptr: *const T) -> Result<(), PointerError>
+    where
+        T: PointerLayout + ?Sized,
+    {
+        if ptr.is_null() {
+            Err(PointerError::Null)
+        } else if ptr as *const u8 as usize & (PointerLayout::align(ptr) - 1) != 0 {
+            Err(PointerError::Unaligned)
+        } else if PointerLayout::size(ptr).is_none() {
+            Err(PointerError::TooLarge)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks that `ptr` is non-null and, with the `layout_for_ptr` feature, that it is properly aligned and
+    /// that the size of its pointee does not exceed `isize::MAX` bytes, without dereferencing the pointer.
+    /// Unlike [PointerError::check], this works for any pointee, because it does not need [PointerLayout].
+    /// Without the `layout_for_ptr` feature, only the null check is performed.
+    ///
+    /// # Safety
+    /// With the `layout_for_ptr` feature, the metadata of `ptr` must be valid if `ptr` is non-null
+    /// (e.g. a valid vtable or an initialized slice length).
+    pub(crate) unsafe fn check_raw<T: ?Sized>(ptr: *const T) -> Result<(), PointerError> {
+        if ptr.is_null() {
+            return Err(PointerError::Null);
+        }
+        #[cfg(feature = "layout_for_ptr")]
+        {
+            // SAFETY: the pointer is non-null and the caller promises that its metadata is valid.
+            let (align, size) = (core::mem::align_of_val_raw(ptr), core::mem::size_of_val_raw(ptr));
+            if ptr as *const u8 as usize & (align - 1) != 0 {
+                return Err(PointerError::Unaligned);
+            } else if size > isize::MAX as usize {
+                return Err(PointerError::TooLarge);
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that `ptr` is non-null and properly aligned and that `len` consecutive elements of
+    /// type `T` do not exceed `isize::MAX` bytes, without dereferencing the pointer.
+    pub(crate) fn check_parts<T>(ptr: *const T, len: usize) -> Result<(), PointerError> {
+        Self::check(ptr)?;
+        slice_size::<T>(len).map(|_| ()).ok_or(PointerError::TooLarge)
+    }
+}
+
+impl core::fmt::Display for PointerError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PointerError::Null => f.write_str("pointer must be non-null"),
+            PointerError::Unaligned => f.write_str("pointer must be aligned"),
+            PointerError::TooLarge => f.write_str("size of the pointee must not exceed isize::MAX"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PointerError {}
@@ -0,0 +1,91 @@
+//! This module contains the [PointerLayout] trait for obtaining the layout of a pointee without dereferencing it.
+
+use crate::PointerLength;
+
+/// A trait which is only implemented for pointers for which the alignment and the size of the pointee
+/// can be determined without creating a reference to the pointee and without accessing the pointee.
+/// This means that the pointer is not dereferenced. This trait is implemented for all sized types
+/// `T` and for slices `[T]`.
+///
+/// # Example
+/// ```
+/// use deferred_reference::PointerLayout;
+/// let array = [0u32; 1024];
+/// assert_eq!(4, PointerLayout::align(core::ptr::addr_of!(array)));
+/// assert_eq!(Some(4096), PointerLayout::size(core::ptr::addr_of!(array)));
+/// ```
+///
+/// # Safety
+/// This trait is unsafe. The implementor must promise never to access or create a reference
+/// to the pointee. The returned alignment must be the alignment that a reference to the pointee
+/// requires and the returned size must be the size that [`core::mem::size_of_val`] would return.
+pub unsafe trait PointerLayout {
+    /// Obtains the alignment of the pointee, without creating an intermediate reference.
+    fn align(ptr: *const Self) -> usize;
+
+    /// Obtains the size of the pointee in bytes, without creating an intermediate reference.
+    /// Returns `None` if the size of the pointee does not fit in an `isize`.
+    ///
+    /// # Panics
+    /// For slices, this method needs the length of the slice. See [PointerLength] for when
+    /// this can panic.
+    fn size(ptr: *const Self) -> Option<usize>;
+}
+
+/// Computes the size in bytes of `len` consecutive elements of type `T`, or returns `None`
+/// if the size does not fit in an `isize`.
+#[inline]
+pub(crate) fn slice_size<T>(len: usize) -> Option<usize> {
+    core::mem::size_of::<T>().checked_mul(len).filter(|&size| size <= isize::MAX as usize)
+}
+
+// SAFETY: the layout of sized types is known at compile time, the pointer is not needed.
+unsafe impl<T> PointerLayout for T {
+    #[inline]
+    fn align(_: *const Self) -> usize {
+        core::mem::align_of::<T>()
+    }
+
+    #[inline]
+    fn size(_: *const Self) -> Option<usize> {
+        Some(core::mem::size_of::<T>())
+    }
+}
+
+// SAFETY: the alignment is known at compile time and the length is obtained
+// SAFETY: through `PointerLength`, which promises not to dereference the pointer.
+unsafe impl<T> PointerLayout for [T] {
+    #[inline]
+    fn align(_: *const Self) -> usize {
+        core::mem::align_of::<T>()
+    }
+
+    #[inline]
+    fn size(ptr: *const Self) -> Option<usize> {
+        slice_size::<T>(PointerLength::len(ptr))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PointerLayout;
+
+    #[test]
+    fn sized() {
+        let value = 0u64;
+        assert_eq!(core::mem::align_of::<u64>(), PointerLayout::align(&value as *const u64));
+        assert_eq!(Some(8), PointerLayout::size(&value as *const u64));
+        assert_eq!(Some(0), PointerLayout::size(&() as *const ()));
+    }
+
+    #[cfg(feature = "slice_ptr_len")]
+    #[test]
+    fn slice() {
+        let buffer = [0u16; 10];
+        let ptr = &buffer[..] as *const [u16];
+        assert_eq!(2, PointerLayout::align(ptr));
+        assert_eq!(Some(20), PointerLayout::size(ptr));
+        let huge = core::ptr::slice_from_raw_parts(buffer.as_ptr(), usize::MAX / 2);
+        assert_eq!(None, PointerLayout::size(huge));
+    }
+}
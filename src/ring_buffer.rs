@@ -0,0 +1,374 @@
+//! This module contains the lock-free single-producer/single-consumer [RingBuffer].
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{Defer, DeferMut, Deferred, SlicePointerIndex};
+
+/// A lock-free single-producer/single-consumer ring buffer, which does not need the `alloc` crate.
+///
+/// The ring buffer is split into a [RingBufferProducer] and a [RingBufferConsumer], which can be sent to
+/// different threads. The producer holds a `Deferred<&mut [T; N]>` and the consumer holds a `Deferred<&[T; N]>`
+/// to the same backing array at the same time. Neither of these are ever dereferenced as a whole. Instead, the
+/// producer hands out deferred mutable references to the free regions of the array and the consumer hands out
+/// deferred immutable references to the filled regions of the array. The head and tail indices of the ring buffer
+/// are atomic and they guarantee that these regions never overlap. A region can wrap around the end of the array,
+/// which is why both sides hand out up to two regions at a time.
+///
+/// The elements of the backing array always remain initialized. Writing to a free slot overwrites (and drops)
+/// the value that was previously stored there and reading from a filled slot does not move the value out of it.
+///
+/// # Example
+/// ```
+/// use deferred_reference::RingBuffer;
+/// let mut ring = RingBuffer::new([0u32; 4]);
+/// let (mut producer, mut consumer) = ring.split();
+/// std::thread::scope(|scope| {
+///     scope.spawn(move || {
+///         for i in 0..100 {
+///             while producer.push(i).is_err() {
+///                 std::hint::spin_loop();
+///             }
+///         }
+///     });
+///     let mut expected = 0;
+///     while expected < 100 {
+///         if let Some(value) = consumer.pop() {
+///             assert_eq!(expected, value);
+///             expected += 1;
+///         }
+///     }
+/// });
+/// ```
+pub struct RingBuffer<T, const N: usize> {
+    /// The backing array of the ring buffer.
+    buffer: UnsafeCell<[T; N]>,
+    /// The position of the first filled slot, which is only ever written to by the consumer.
+    /// Positions are kept in the range `0..2 * N`, so that a full buffer can be told apart from an empty one.
+    head: AtomicUsize,
+    /// The position of the first free slot, which is only ever written to by the producer.
+    tail: AtomicUsize,
+}
+
+/// The producing half of a [RingBuffer], see [RingBuffer::split].
+pub struct RingBufferProducer<'a, T, const N: usize> {
+    /// A deferred mutable reference to the backing array, which is only dereferenced in the free regions.
+    buffer: Deferred<&'a mut [T; N]>,
+    /// The position of the first filled slot.
+    head: &'a AtomicUsize,
+    /// The position of the first free slot.
+    tail: &'a AtomicUsize,
+}
+
+/// The consuming half of a [RingBuffer], see [RingBuffer::split].
+pub struct RingBufferConsumer<'a, T, const N: usize> {
+    /// A deferred immutable reference to the backing array, which is only dereferenced in the filled regions.
+    buffer: Deferred<&'a [T; N]>,
+    /// The position of the first filled slot.
+    head: &'a AtomicUsize,
+    /// The position of the first free slot.
+    tail: &'a AtomicUsize,
+}
+
+// SAFETY: the consumer only ever reads slots that the producer has stopped accessing, so the values
+// SAFETY: are effectively moved from the producer to the consumer. This only requires `T: Send`,
+// SAFETY: whereas the auto trait implementation would require `T: Sync` due to the `Deferred<&[T; N]>`.
+unsafe impl<T: Send, const N: usize> Send for RingBufferConsumer<'_, T, N> {}
+
+/// Returns the number of slots between the positions `head` and `tail` of a ring buffer with capacity `N`.
+#[inline]
+fn distance<const N: usize>(head: usize, tail: usize) -> usize {
+    if tail >= head { tail - head } else { tail + 2 * N - head }
+}
+
+/// Moves the position `index` of a ring buffer with capacity `N` forward by `n <= N` slots.
+#[inline]
+fn advance<const N: usize>(index: usize, n: usize) -> usize {
+    let index = index + n;
+    if index >= 2 * N { index - 2 * N } else { index }
+}
+
+/// Returns the ranges of the (up to two) regions with `len <= N` slots starting at position `index`
+/// of a ring buffer with capacity `N`.
+#[inline]
+fn regions<const N: usize>(index: usize, len: usize) -> (core::ops::Range<usize>, core::ops::Range<usize>) {
+    let start = if index >= N { index - N } else { index };
+    if start + len <= N {
+        (start..start + len, 0..0)
+    } else {
+        (start..N, 0..start + len - N)
+    }
+}
+
+impl<T, const N: usize> RingBuffer<T, N> {
+    /// Creates a new and empty ring buffer with `buffer` as its backing array.
+    /// The initial values in `buffer` are never read, they only get overwritten by the producer.
+    ///
+    /// # Panics
+    /// Panics if `N` exceeds `isize::MAX`.
+    pub fn new(buffer: [T; N]) -> Self {
+        assert!(N <= isize::MAX as usize, "capacity of the ring buffer must not exceed isize::MAX");
+        Self {
+            buffer: UnsafeCell::new(buffer),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of elements that the ring buffer can hold.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the number of elements in the ring buffer.
+    pub fn len(&self) -> usize {
+        distance::<N>(self.head.load(Ordering::Acquire), self.tail.load(Ordering::Acquire))
+    }
+
+    /// Returns `true` if the ring buffer contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Splits the ring buffer into its producing half and its consuming half.
+    /// The ring buffer can be split again after both halves have been dropped,
+    /// the elements in the ring buffer are kept in between.
+    pub fn split(&mut self) -> (RingBufferProducer<'_, T, N>, RingBufferConsumer<'_, T, N>) {
+        // SAFETY: the producer and the consumer only dereference disjoint regions of the buffer.
+        let buffer_mut = unsafe { self.buffer.defer_mut() };
+        (
+            RingBufferProducer {
+                buffer: buffer_mut,
+                head: &self.head,
+                tail: &self.tail,
+            },
+            RingBufferConsumer {
+                buffer: self.buffer.defer(),
+                head: &self.head,
+                tail: &self.tail,
+            },
+        )
+    }
+
+    /// Consumes the ring buffer and returns its backing array.
+    pub fn into_inner(self) -> [T; N] {
+        self.buffer.into_inner()
+    }
+}
+
+impl<T, const N: usize> RingBufferProducer<'_, T, N> {
+    /// Returns the number of free slots in the ring buffer. The consumer may free up more slots
+    /// at any time, so this is a lower bound.
+    pub fn free_len(&self) -> usize {
+        N - distance::<N>(self.head.load(Ordering::Acquire), self.tail.load(Ordering::Relaxed))
+    }
+
+    /// Returns deferred mutable references to the free regions of the ring buffer. The second region is only
+    /// non-empty if the free slots wrap around the end of the backing array. Writing to the free regions has
+    /// no effect until the written slots are passed on to the consumer with [RingBufferProducer::commit].
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::RingBuffer;
+    /// let mut ring = RingBuffer::new([0u8; 4]);
+    /// let (mut producer, mut consumer) = ring.split();
+    /// producer.commit(3);
+    /// consumer.release(3);
+    /// let (mut first, mut second) = producer.write_regions();
+    /// // dereferencing the regions first makes this work without the `slice_ptr_len` feature, too
+    /// assert_eq!((1, 3), ((*first).len(), (*second).len()));
+    /// first.copy_from_slice(&[1]);
+    /// second.copy_from_slice(&[2, 3, 4]);
+    /// producer.commit(4);
+    /// let (first, second) = consumer.read_regions();
+    /// assert_eq!((&[1][..], &[2, 3, 4][..]), (&*first, &*second));
+    /// ```
+    pub fn write_regions(&mut self) -> (Deferred<&mut [T]>, Deferred<&mut [T]>) {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let (first, second) = regions::<N>(tail, self.free_len());
+        // SAFETY: both ranges are in bounds and they only cover free slots, which the consumer
+        // SAFETY: does not access. They are borrowed from `self`, so they can not outlive a commit.
+        unsafe {
+            (
+                Deferred::from_raw_mut(first.get_unchecked_mut(self.buffer.as_mut_ptr())),
+                Deferred::from_raw_mut(second.get_unchecked_mut(self.buffer.as_mut_ptr())),
+            )
+        }
+    }
+
+    /// Passes the first `len` free slots on to the consumer.
+    ///
+    /// # Panics
+    /// Panics if `len` exceeds the number of free slots.
+    pub fn commit(&mut self, len: usize) {
+        assert!(len <= self.free_len(), "cannot commit more slots than are free");
+        let tail = self.tail.load(Ordering::Relaxed);
+        self.tail.store(advance::<N>(tail, len), Ordering::Release);
+    }
+
+    /// Appends `value` to the ring buffer, or returns `value` back if the ring buffer is full.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        if self.free_len() == 0 {
+            return Err(value);
+        }
+        let (first, _) = regions::<N>(self.tail.load(Ordering::Relaxed), 1);
+        // SAFETY: the slot is in bounds and it is free, so the consumer does not access it.
+        unsafe { *self.buffer.get_unchecked_mut(first.start) = value };
+        self.commit(1);
+        Ok(())
+    }
+}
+
+impl<T, const N: usize> RingBufferConsumer<'_, T, N> {
+    /// Returns the number of filled slots in the ring buffer. The producer may fill up more slots
+    /// at any time, so this is a lower bound.
+    pub fn len(&self) -> usize {
+        distance::<N>(self.head.load(Ordering::Relaxed), self.tail.load(Ordering::Acquire))
+    }
+
+    /// Returns `true` if there are no filled slots in the ring buffer.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns deferred immutable references to the filled regions of the ring buffer, in the order in which
+    /// they were filled. The second region is only non-empty if the filled slots wrap around the end of the
+    /// backing array. The slots are handed back to the producer with [RingBufferConsumer::release].
+    pub fn read_regions(&self) -> (Deferred<&[T]>, Deferred<&[T]>) {
+        let head = self.head.load(Ordering::Relaxed);
+        let (first, second) = regions::<N>(head, self.len());
+        // SAFETY: both ranges are in bounds and they only cover filled slots, which the producer
+        // SAFETY: does not access. They are borrowed from `self`, so they can not outlive a release.
+        unsafe {
+            (
+                Deferred::from_raw(first.get_unchecked(self.buffer.as_ptr())),
+                Deferred::from_raw(second.get_unchecked(self.buffer.as_ptr())),
+            )
+        }
+    }
+
+    /// Hands the first `len` filled slots back to the producer.
+    ///
+    /// # Panics
+    /// Panics if `len` exceeds the number of filled slots.
+    pub fn release(&mut self, len: usize) {
+        assert!(len <= self.len(), "cannot release more slots than are filled");
+        let head = self.head.load(Ordering::Relaxed);
+        self.head.store(advance::<N>(head, len), Ordering::Release);
+    }
+
+    /// Removes the first element from the ring buffer and returns a clone of it,
+    /// or returns `None` if the ring buffer is empty.
+    pub fn pop(&mut self) -> Option<T>
+    where
+        T: Clone,
+    {
+        if self.is_empty() {
+            return None;
+        }
+        let (first, _) = regions::<N>(self.head.load(Ordering::Relaxed), 1);
+        // SAFETY: the slot is in bounds and it is filled, so the producer does not access it.
+        let value = unsafe { self.buffer.get_unchecked(first.start) }.clone();
+        self.release(1);
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::RingBuffer;
+
+    #[test]
+    fn push_pop() {
+        let mut ring = RingBuffer::new([0u8; 3]);
+        {
+            let (mut producer, mut consumer) = ring.split();
+            assert_eq!(None, consumer.pop());
+            for round in 0..5 {
+                assert_eq!(Ok(()), producer.push(round));
+                assert_eq!(Ok(()), producer.push(round + 1));
+                assert_eq!(Ok(()), producer.push(round + 2));
+                assert_eq!(Err(42), producer.push(42));
+                assert_eq!(0, producer.free_len());
+                assert_eq!(3, consumer.len());
+                assert_eq!(Some(round), consumer.pop());
+                assert_eq!(Some(round + 1), consumer.pop());
+                assert_eq!(Some(round + 2), consumer.pop());
+                assert!(consumer.is_empty());
+            }
+        }
+        assert!(ring.is_empty());
+        assert_eq!(3, ring.capacity());
+    }
+
+    #[cfg(feature = "slice_ptr_len")]
+    #[test]
+    fn regions() {
+        let mut ring = RingBuffer::new([0u16; 4]);
+        {
+            let (mut producer, mut consumer) = ring.split();
+            producer.commit(3);
+            consumer.release(2);
+            let (mut first, mut second) = producer.write_regions();
+            // the write regions and the filled slot are all alive at the same time
+            let (read, _) = consumer.read_regions();
+            let (first_ref, second_ref, read_ref) = (&mut first[..], &mut second[..], &read[..]);
+            assert_eq!((1, 2, 1), (first_ref.len(), second_ref.len(), read_ref.len()));
+            first_ref[0] = 7;
+            second_ref[1] = 9;
+            producer.commit(3);
+            let (first, second) = consumer.read_regions();
+            assert_eq!((&[0, 7][..], &[0, 9][..]), (&first[..], &second[..]));
+            consumer.release(4);
+        }
+        assert_eq!([0, 9, 0, 7], ring.into_inner());
+    }
+
+    #[test]
+    #[should_panic]
+    fn commit_too_many() {
+        let mut ring = RingBuffer::new([0u8; 2]);
+        ring.split().0.commit(3);
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let mut ring = RingBuffer::new([0u8; 0]);
+        let (mut producer, mut consumer) = ring.split();
+        assert_eq!(Err(1), producer.push(1));
+        assert_eq!(None, consumer.pop());
+    }
+
+    #[test]
+    fn threads() {
+        extern crate std;
+        use alloc::string::{String, ToString};
+        let slots = [String::new(), String::new(), String::new(), String::new(), String::new(), String::new(), String::new()];
+        let mut ring = RingBuffer::new(slots);
+        let (mut producer, mut consumer) = ring.split();
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                for i in 0..1000 {
+                    let mut value = i.to_string();
+                    while let Err(rejected) = producer.push(value) {
+                        value = rejected;
+                        std::thread::yield_now();
+                    }
+                }
+            });
+            scope.spawn(move || {
+                let mut expected = 0;
+                while expected < 1000 {
+                    match consumer.pop() {
+                        Some(value) => {
+                            assert_eq!(expected.to_string(), value);
+                            expected += 1;
+                        }
+                        None => std::thread::yield_now(),
+                    }
+                }
+            });
+        });
+        assert!(ring.is_empty());
+    }
+}
@@ -0,0 +1,285 @@
+//! This module contains the [SharedRegion] struct, which shares deferred slices of [Pod] types between processes.
+
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+use std::fs::File;
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+
+use crate::{Deferred, DeferredMmap, Pod};
+
+/// A region of shared memory which is backed by a Linux `memfd` and which can be shared between processes.
+/// The region is viewed as a deferred slice of [Pod] elements (i.e. `Deferred<&[T]>` and `Deferred<&mut [T]>`),
+/// which can be split into disjoint segments with [SharedRegion::segments_mut] and [SharedRegion::split_at_mut].
+/// This requires the `mmap` feature and is only available on Linux.
+///
+/// A region is created with [SharedRegion::create]. Another process obtains the same region either by inheriting
+/// it through `fork`, or by receiving the file descriptor (e.g. through a Unix socket or by opening
+/// `/proc/<pid>/fd/<fd>`) and passing it to [SharedRegion::open].
+///
+/// # Cross-process aliasing rules
+/// The Rust compiler does not know that the memory of a region is shared with other processes, so it is up to
+/// the processes to uphold the same rules that apply to deferred references which are shared between threads:
+/// * every process must treat its [SharedRegion] as if it were a copy of the same deferred mutable reference
+///   that was created with [Deferred::clone_unchecked]. In particular, a process must not dereference any part
+///   of the region while another process is mutating the same part.
+/// * the processes must agree on which process accesses which part of the region, e.g. by deciding this before
+///   calling `fork` or by passing indices to the segments over a pipe or a socket.
+/// * handing over a part of the region to another process requires synchronization (e.g. a pipe, a socket or
+///   `waitpid`), which establishes that the writes of one process are visible to the other process.
+///
+/// The elements of a region are [Pod], so every byte that another process writes is a valid byte of `T`.
+///
+/// # Example
+/// ```
+/// use deferred_reference::SharedRegion;
+/// let mut region = SharedRegion::<u32>::create(1024).unwrap();
+/// // the first half is written by the parent and the second half is written by the child.
+/// match unsafe { libc::fork() } {
+///     0 => {
+///         region.segments_mut(512).nth(1).unwrap().fill(2);
+///         unsafe { libc::_exit(0) };
+///     }
+///     child => {
+///         region.segments_mut(512).next().unwrap().fill(1);
+///         assert_eq!(child, unsafe { libc::waitpid(child, core::ptr::null_mut(), 0) });
+///     }
+/// }
+/// // dereferencing before indexing makes this work without the `slice_ptr_len` feature, too
+/// let values = &*region.as_deferred();
+/// assert_eq!([1, 2], [values[0], values[1023]]);
+/// ```
+pub struct SharedRegion<T: Pod> {
+    /// The memfd which backs the region.
+    file: File,
+    /// The shared mapping of `file`.
+    mmap: DeferredMmap,
+    /// The number of elements in the region.
+    len: usize,
+    /// The element type of the region.
+    _marker: PhantomData<T>,
+}
+
+impl<T: Pod> SharedRegion<T> {
+    /// Creates a new region of `len` elements, which are initialized to zero.
+    /// The memfd of the region is shared with child processes through `fork`, but it is closed on `exec`
+    /// (i.e. it is created with `MFD_CLOEXEC`), so that it does not leak into unrelated programs.
+    ///
+    /// # Errors
+    /// Returns an error if the memfd can not be created or mapped.
+    ///
+    /// # Panics
+    /// Panics if `T` is a zero-sized type or if the size of the region in bytes overflows.
+    pub fn create(len: usize) -> io::Result<Self> {
+        assert!(core::mem::size_of::<T>() != 0, "SharedRegion does not support zero-sized types");
+        let size = len.checked_mul(core::mem::size_of::<T>()).expect("SharedRegion size overflow");
+        // SAFETY: the name is a valid C string.
+        let fd = unsafe { libc::memfd_create(b"deferred-reference\0".as_ptr() as *const libc::c_char, libc::MFD_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the file descriptor was just created and it is not owned by anything else.
+        let file = unsafe { File::from_raw_fd(fd) };
+        file.set_len(size as u64)?;
+        // SAFETY: the memfd is new, so it is not shared with anything else yet.
+        unsafe { Self::open(file) }
+    }
+
+    /// Opens a region which was created by another process, e.g. through a file descriptor which was
+    /// inherited or received from that process.
+    ///
+    /// # Errors
+    /// Returns an error if the size of the file is not a multiple of the size of `T` or if the file can not be mapped.
+    ///
+    /// # Panics
+    /// Panics if `T` is a zero-sized type.
+    ///
+    /// # Safety
+    /// The caller must follow the cross-process aliasing rules of [SharedRegion], and the file must not be truncated
+    /// for as long as the region exists.
+    pub unsafe fn open(file: File) -> io::Result<Self> {
+        assert!(core::mem::size_of::<T>() != 0, "SharedRegion does not support zero-sized types");
+        let mmap = DeferredMmap::map(&file)?;
+        let len = mmap.len() / core::mem::size_of::<T>();
+        if len * core::mem::size_of::<T>() != mmap.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "size of the region must be a multiple of the size of the element type"));
+        }
+        // an empty mapping has a dangling pointer, which is replaced by `NonNull::<T>::dangling` (see `ptr`).
+        if len != 0 && mmap.as_deferred().as_ptr() as *const u8 as usize & (core::mem::align_of::<T>() - 1) != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "region must be aligned for the element type"));
+        }
+        Ok(Self { file, mmap, len, _marker: PhantomData })
+    }
+
+    /// Returns a pointer to the first element of the region, which is dangling (but aligned) if the region is empty.
+    fn ptr(&self) -> *const T {
+        if self.len == 0 {
+            NonNull::dangling().as_ptr()
+        } else {
+            self.mmap.as_deferred().as_ptr() as *const T
+        }
+    }
+
+    /// Returns a mutable pointer to the first element of the region, which is dangling (but aligned) if the region is empty.
+    fn ptr_mut(&mut self) -> *mut T {
+        if self.len == 0 {
+            NonNull::dangling().as_ptr()
+        } else {
+            self.mmap.as_deferred_mut().as_mut_ptr() as *mut T
+        }
+    }
+
+    /// Returns the number of elements in the region.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the region has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the memfd which backs the region, e.g. for sending it to another process.
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    /// Returns a deferred immutable reference to the region.
+    pub fn as_deferred(&self) -> Deferred<&[T]> {
+        // SAFETY: the mapping is aligned for `T` and it contains exactly `self.len` elements.
+        unsafe { Deferred::from_raw_parts(self.ptr(), self.len) }
+    }
+
+    /// Returns a deferred mutable reference to the region.
+    pub fn as_deferred_mut(&mut self) -> Deferred<&mut [T]> {
+        // SAFETY: the mapping is aligned for `T` and it contains exactly `self.len` elements.
+        unsafe { Deferred::from_raw_parts_mut(self.ptr_mut(), self.len) }
+    }
+
+    /// Splits the region into two disjoint deferred mutable references at element index `mid`.
+    ///
+    /// # Panics
+    /// Panics if `mid > len`.
+    pub fn split_at_mut(&mut self, mid: usize) -> (Deferred<&mut [T]>, Deferred<&mut [T]>) {
+        assert!(mid <= self.len, "mid > len");
+        let ptr = self.ptr_mut();
+        // SAFETY: both halves are disjoint and they lie within the region, which is mutably borrowed.
+        unsafe {
+            (
+                Deferred::from_raw_parts_mut(ptr, mid),
+                Deferred::from_raw_parts_mut(ptr.add(mid), self.len - mid),
+            )
+        }
+    }
+
+    /// Splits the region into disjoint segments of `segment_len` elements, which can be accessed at the same time.
+    /// The last segment is shorter if the length of the region is not a multiple of `segment_len`.
+    ///
+    /// # Panics
+    /// Panics if `segment_len` is 0.
+    pub fn segments_mut(&mut self, segment_len: usize) -> impl DoubleEndedIterator<Item = Deferred<&mut [T]>> + ExactSizeIterator + '_ {
+        assert!(segment_len != 0, "segment length must be non-zero");
+        let (ptr, len) = (self.ptr_mut(), self.len);
+        (0..len / segment_len + (len % segment_len != 0) as usize).map(move |index| {
+            let start = index * segment_len;
+            // SAFETY: the segments are disjoint and they all lie within the region, which is mutably borrowed.
+            unsafe { Deferred::from_raw_parts_mut(ptr.add(start), segment_len.min(len - start)) }
+        })
+    }
+}
+
+impl<T: Pod> AsRawFd for SharedRegion<T> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl<T: Pod> core::fmt::Debug for SharedRegion<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SharedRegion").field("fd", &self.as_raw_fd()).field("len", &self.len).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::ErrorKind;
+    use crate::SharedRegion;
+
+    #[test]
+    fn open_by_path() {
+        let mut region = SharedRegion::<u64>::create(16).unwrap();
+        let path = format!("/proc/self/fd/{}", std::os::unix::io::AsRawFd::as_raw_fd(&region));
+        let file = std::fs::OpenOptions::new().read(true).write(true).open(path).unwrap();
+        let mut other = unsafe { SharedRegion::<u64>::open(file) }.unwrap();
+        {
+            let (mut left, _) = region.split_at_mut(8);
+            let (_, mut right) = other.split_at_mut(8);
+            left.fill(1);
+            right.fill(2);
+        }
+        assert_eq!(16, other.len());
+        assert_eq!(1, (*other.as_deferred())[7]);
+        assert_eq!(2, (*region.as_deferred())[8]);
+    }
+
+    /// The environment variable which marks the re-executed test binary that runs the `fork` test on its own.
+    const FORK_TEST: &str = "DEFERRED_REFERENCE_FORK_TEST";
+
+    #[test]
+    fn fork() {
+        // note: the test harness runs the other tests on other threads, which may hold locks while `fork` is called.
+        // Therefore the test binary re-executes itself, so that the `fork` test runs without any other tests.
+        if std::env::var_os(FORK_TEST).is_none() {
+            #[allow(clippy::needless_borrows_for_generic_args)] // arrays implement `IntoIterator` by value since Rust 1.53
+            let output = std::process::Command::new(std::env::current_exe().unwrap())
+                .args(&["--exact", "shared_region::tests::fork", "--test-threads=1"])
+                .env(FORK_TEST, "1")
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{}", std::string::String::from_utf8_lossy(&output.stdout));
+            return;
+        }
+        let mut region = SharedRegion::<u32>::create(100).unwrap();
+        let child = unsafe { libc::fork() };
+        assert!(child >= 0);
+        if child == 0 {
+            for (index, mut segment) in region.segments_mut(10).enumerate().skip(5) {
+                segment.fill(index as u32);
+            }
+            unsafe { libc::_exit(0) };
+        }
+        for (index, mut segment) in region.segments_mut(10).enumerate().take(5) {
+            segment.fill(index as u32);
+        }
+        let mut status = 0;
+        assert_eq!(child, unsafe { libc::waitpid(child, &mut status, 0) });
+        assert_eq!(0, status);
+        let values = &*region.as_deferred();
+        assert!((0..100).all(|index| values[index] == index as u32 / 10));
+    }
+
+    #[test]
+    fn size_mismatch() {
+        let region = SharedRegion::<u8>::create(3).unwrap();
+        let file: File = region.file().try_clone().unwrap();
+        assert_eq!(ErrorKind::InvalidData, unsafe { SharedRegion::<u16>::open(file) }.unwrap_err().kind());
+    }
+
+    #[test]
+    fn empty() {
+        let mut region = SharedRegion::<u32>::create(0).unwrap();
+        assert!(region.is_empty());
+        assert_eq!(0, region.segments_mut(4).len());
+        let (left, right) = region.split_at_mut(0);
+        assert_eq!((0, 0), ((*left).len(), (*right).len()));
+        assert_eq!(0, (*region.as_deferred()).len());
+    }
+
+    #[test]
+    fn close_on_exec() {
+        let region = SharedRegion::<u8>::create(1).unwrap();
+        let flags = unsafe { libc::fcntl(std::os::unix::io::AsRawFd::as_raw_fd(&region), libc::F_GETFD) };
+        assert_eq!(libc::FD_CLOEXEC, flags & libc::FD_CLOEXEC);
+    }
+}
@@ -126,7 +126,7 @@ where
 
     #[inline]
     fn index(self, slice: *const T) -> *const Self::Output {
-        if self > PointerLength::len(slice) {
+        if self >= PointerLength::len(slice) {
             slice_index_overflow_fail(self, PointerLength::len(slice))
         }
         // SAFETY: this is safe, bounds are checked above
@@ -137,7 +137,7 @@ where
 
     #[inline]
     fn index_mut(self, slice: *mut T) -> *mut Self::Output {
-        if self > PointerLength::len(slice) {
+        if self >= PointerLength::len(slice) {
             slice_index_overflow_fail(self, PointerLength::len(slice))
         }
         // SAFETY: this is safe, bounds are checked above
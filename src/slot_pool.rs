@@ -0,0 +1,305 @@
+//! This module contains the [SlotPool] struct, a fixed-capacity object pool which hands out deferred mutable references to its slots.
+
+use core::cell::{Cell, UnsafeCell};
+use core::mem::MaybeUninit;
+
+use crate::Deferred;
+
+/// The bookkeeping of a slot of a [SlotPool].
+#[derive(Clone, Copy)]
+struct Meta {
+    /// The generation of the slot, which is incremented every time a value is removed from the slot.
+    generation: u32,
+    /// `true` if the slot holds a value.
+    occupied: bool,
+    /// The index of the next free slot, if this slot is free. This is `N` at the end of the free list.
+    next_free: usize,
+}
+
+/// A copyable identifier of a slot of a [SlotPool] and the generation of its value, see [SlotKey::id].
+/// The identifier becomes stale when the value is removed from the pool, which can be checked with [SlotPool::contains].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SlotId {
+    /// The index of the slot.
+    pub index: usize,
+    /// The generation of the value in the slot.
+    pub generation: u32,
+}
+
+/// The key to a value in a [SlotPool], which is returned by [SlotPool::insert] and consumed by [SlotPool::remove].
+/// The key is the only way to access its value, so it can not be copied: a mutable borrow of the key is needed
+/// to obtain a deferred mutable reference to the value (see [SlotPool::get_mut]). The key borrows the pool,
+/// so the pool can not be moved or dropped while any of its keys are still alive.
+///
+/// A key can not hand out two deferred mutable references to its value at the same time:
+/// ```compile_fail
+/// use deferred_reference::SlotPool;
+/// let pool = SlotPool::<u32, 1>::new();
+/// let mut key = pool.insert(0).unwrap();
+/// let (a, b) = (pool.get_mut(&mut key), pool.get_mut(&mut key));
+/// ```
+pub struct SlotKey<'pool, T, const N: usize> {
+    /// The pool that the value belongs to.
+    pool: &'pool SlotPool<T, N>,
+    /// The slot and the generation of the value.
+    id: SlotId,
+}
+
+impl<T, const N: usize> SlotKey<'_, T, N> {
+    /// Returns the copyable identifier of the slot and the generation of the value.
+    pub fn id(&self) -> SlotId {
+        self.id
+    }
+}
+
+impl<T, const N: usize> core::fmt::Debug for SlotKey<'_, T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("SlotKey").field(&self.id).finish()
+    }
+}
+
+/// A fixed-capacity object pool with `N` slots, which is backed by an `UnsafeCell<[MaybeUninit<T>; N]>` and a free list.
+/// Inserting a value returns a generational [SlotKey] and [SlotPool::get_mut] turns a mutably borrowed key into a
+/// deferred mutable reference to its value. Values are inserted and removed through a shared reference to the pool,
+/// so the deferred references to different slots can be used at the same time, and values can be inserted or removed
+/// while other values are in use. The values never move, so the deferred references are stable, pointer-like references
+/// to the pooled values, which do not lock the whole pool (unlike indexing into a `Vec<T>`).
+///
+/// The pool does not need the `alloc` crate. It can not be shared between threads (i.e. it is not [Sync]),
+/// because its free list is not synchronized. The values that are still in the pool are dropped together with the pool.
+///
+/// # Example
+/// ```
+/// use deferred_reference::SlotPool;
+/// let pool = SlotPool::<String, 4>::new();
+/// let mut hello = pool.insert(String::from("hello")).unwrap();
+/// let mut world = pool.insert(String::from("world")).unwrap();
+/// // the deferred references to different slots can be used at the same time:
+/// let (mut a, mut b) = (pool.get_mut(&mut hello), pool.get_mut(&mut world));
+/// core::mem::swap(&mut *a, &mut *b);
+/// a.push('!');
+/// let stale = world.id();
+/// assert_eq!("hello", pool.remove(world));
+/// assert!(!pool.contains(stale));
+/// // the slot is reused with a new generation:
+/// let again = pool.insert(String::new()).unwrap();
+/// assert_eq!(stale.index, again.id().index);
+/// assert_ne!(stale.generation, again.id().generation);
+/// assert_eq!("world!", &*pool.get(&hello));
+/// ```
+pub struct SlotPool<T, const N: usize> {
+    /// The slots of the pool.
+    values: UnsafeCell<[MaybeUninit<T>; N]>,
+    /// The bookkeeping of the slots.
+    meta: [Cell<Meta>; N],
+    /// The index of the first free slot, or `N` if the pool is full.
+    free: Cell<usize>,
+    /// The number of values in the pool.
+    len: Cell<usize>,
+}
+
+impl<T, const N: usize> SlotPool<T, N> {
+    /// Creates a new empty pool.
+    pub fn new() -> Self {
+        /// The bookkeeping of a free slot, before it is linked into the free list.
+        #[allow(clippy::declare_interior_mutable_const)] // the constant is only used to initialize the array
+        const FREE: Cell<Meta> = Cell::new(Meta { generation: 0, occupied: false, next_free: 0 });
+        let meta = [FREE; N];
+        // link every slot to the next one, so that the free list initially holds all slots in order
+        for (index, meta) in meta.iter().enumerate() {
+            meta.set(Meta { next_free: index + 1, ..meta.get() });
+        }
+        Self {
+            // SAFETY: an array of `MaybeUninit` does not need to be initialized.
+            values: UnsafeCell::new(unsafe { MaybeUninit::uninit().assume_init() }),
+            meta,
+            free: Cell::new(0),
+            len: Cell::new(0),
+        }
+    }
+
+    /// Returns the number of values in the pool.
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    /// Returns `true` if the pool holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.len.get() == 0
+    }
+
+    /// Returns the number of slots of the pool, which is `N`.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns `true` if the value that `id` refers to is still in the pool, i.e. if `id` is not stale.
+    #[allow(clippy::unnecessary_map_or)] // `Option::is_some_and` requires Rust 1.70
+    pub fn contains(&self, id: SlotId) -> bool {
+        self.meta.get(id.index).map_or(false, |meta| {
+            let meta = meta.get();
+            meta.occupied && meta.generation == id.generation
+        })
+    }
+
+    /// Moves `value` into a free slot and returns the key to it.
+    ///
+    /// # Errors
+    /// Returns `value` back if all slots are occupied.
+    pub fn insert(&self, value: T) -> Result<SlotKey<'_, T, N>, T> {
+        let index = self.free.get();
+        let meta = match self.meta.get(index) {
+            Some(meta) => meta,
+            None => return Err(value),
+        };
+        let Meta { generation, next_free, .. } = meta.get();
+        // SAFETY: the slot is free, so there are no deferred references to it.
+        unsafe { self.slot(index).write(value) };
+        meta.set(Meta { generation, occupied: true, next_free });
+        self.free.set(next_free);
+        self.len.set(self.len.get() + 1);
+        Ok(SlotKey { pool: self, id: SlotId { index, generation } })
+    }
+
+    /// Returns a pointer to the slot at `index`, without creating any references to the slots.
+    fn slot(&self, index: usize) -> *mut T {
+        debug_assert!(index < N);
+        // SAFETY: the index is in bounds, so the pointer stays within the array.
+        unsafe { (self.values.get() as *mut T).add(index) }
+    }
+
+    /// Checks that `key` belongs to this pool.
+    #[track_caller]
+    fn check(&self, key: &SlotKey<'_, T, N>) {
+        assert!(core::ptr::eq(self, key.pool), "SlotPool: key belongs to another pool");
+    }
+
+    /// Returns a deferred reference to the value of `key`.
+    ///
+    /// # Panics
+    /// Panics if `key` belongs to another pool.
+    #[track_caller]
+    pub fn get<'k>(&self, key: &'k SlotKey<'_, T, N>) -> Deferred<&'k T> {
+        self.check(key);
+        // SAFETY: the key is borrowed, so there is no deferred mutable reference to the value (see `get_mut`).
+        // SAFETY: the value can not be removed while the key is borrowed, because `remove` consumes the key.
+        unsafe { Deferred::from_raw(self.slot(key.id.index)) }
+    }
+
+    /// Returns a deferred mutable reference to the value of `key`. The key is mutably borrowed for as long as the
+    /// deferred reference is alive, so there can not be any other deferred references to the same value.
+    /// Deferred references to the values of other keys can be used at the same time.
+    ///
+    /// # Panics
+    /// Panics if `key` belongs to another pool.
+    #[track_caller]
+    pub fn get_mut<'k>(&self, key: &'k mut SlotKey<'_, T, N>) -> Deferred<&'k mut T> {
+        self.check(key);
+        // SAFETY: the key is the only way to access the value and it is mutably borrowed,
+        // SAFETY: so this is the only deferred reference to the value.
+        unsafe { Deferred::from_raw_mut(self.slot(key.id.index)) }
+    }
+
+    /// Removes the value of `key` from the pool and returns it. This makes the slot available for another value
+    /// and it increments the generation of the slot, which makes the [SlotId] of the key stale.
+    ///
+    /// # Panics
+    /// Panics if `key` belongs to another pool.
+    #[track_caller]
+    pub fn remove(&self, key: SlotKey<'_, T, N>) -> T {
+        self.check(&key);
+        let index = key.id.index;
+        // SAFETY: the key is consumed, so there are no deferred references to the value anymore.
+        let value = unsafe { self.slot(index).read() };
+        let generation = key.id.generation.wrapping_add(1);
+        self.meta[index].set(Meta { generation, occupied: false, next_free: self.free.get() });
+        self.free.set(index);
+        self.len.set(self.len.get() - 1);
+        value
+    }
+}
+
+impl<T, const N: usize> Default for SlotPool<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for SlotPool<T, N> {
+    fn drop(&mut self) {
+        for (index, meta) in self.meta.iter().enumerate() {
+            if meta.get().occupied {
+                // SAFETY: the slot holds a value and there are no keys left, because keys borrow the pool.
+                unsafe { core::ptr::drop_in_place(self.slot(index)) }
+            }
+        }
+    }
+}
+
+impl<T, const N: usize> core::fmt::Debug for SlotPool<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SlotPool").field("len", &self.len()).field("capacity", &N).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::rc::Rc;
+    use alloc::vec::Vec;
+    use crate::SlotPool;
+
+    #[test]
+    fn insert_and_remove() {
+        let pool = SlotPool::<u32, 3>::new();
+        let mut keys: Vec<_> = (0..3).map(|value| pool.insert(value).unwrap()).collect();
+        assert_eq!(Err(3), pool.insert(3).map(|_| ()));
+        for key in keys.iter_mut() {
+            *pool.get_mut(key) += 10;
+        }
+        let first = keys.remove(0);
+        let id = first.id();
+        assert!(pool.contains(id));
+        assert_eq!(10, pool.remove(first));
+        assert!(!pool.contains(id));
+        let key = pool.insert(20).unwrap();
+        assert_eq!(0, key.id().index);
+        assert_eq!(1, key.id().generation);
+        assert_eq!(20, *pool.get(&key));
+        assert_eq!(3, pool.len());
+        assert_eq!("SlotPool { len: 3, capacity: 3 }", format!("{:?}", pool));
+    }
+
+    #[test]
+    fn simultaneous() {
+        let pool = SlotPool::<[u8; 4], 8>::default();
+        let mut keys: Vec<_> = (0..8).map(|value| pool.insert([value; 4]).unwrap()).collect();
+        let mut deferred: Vec<_> = keys.iter_mut().map(|key| pool.get_mut(key)).collect();
+        let (left, right) = deferred.split_at_mut(4);
+        for (a, b) in left.iter_mut().zip(right.iter_mut()) {
+            core::mem::swap(&mut **a, &mut **b);
+        }
+        assert_eq!([4; 4], *deferred[0]);
+        assert_eq!([3; 4], *deferred[7]);
+    }
+
+    #[test]
+    fn drop_values() {
+        let rc = Rc::new(());
+        {
+            let pool = SlotPool::<Rc<()>, 4>::new();
+            let key = pool.insert(rc.clone()).unwrap();
+            pool.insert(rc.clone()).unwrap();
+            drop(pool.remove(key));
+            assert_eq!(2, Rc::strong_count(&rc));
+        }
+        assert_eq!(1, Rc::strong_count(&rc));
+    }
+
+    #[test]
+    #[should_panic]
+    fn other_pool() {
+        let (first, second) = (SlotPool::<u8, 1>::new(), SlotPool::<u8, 1>::new());
+        let key = first.insert(0).unwrap();
+        second.remove(key);
+    }
+}
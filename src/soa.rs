@@ -0,0 +1,429 @@
+//! This module contains the [Soa] struct-of-arrays container, its [SoaColumn] columns and the [SoaRecord] trait for its records.
+
+use core::cell::{Cell, UnsafeCell};
+use core::mem::MaybeUninit;
+use std::boxed::Box;
+use std::vec::Vec;
+
+use crate::Deferred;
+
+/// A fixed-capacity column of a [Soa], which stores one field of each record in an `UnsafeCell<[MaybeUninit<T>]>`.
+/// The column does not know how many of its fields are initialized, this is tracked by the [Soa] that owns it.
+/// The columns are created by the code that `#[derive(SoaRecord)]` generates and they should not be used directly.
+pub struct SoaColumn<T> {
+    /// The fields of the column, of which only the first `len` (as tracked by the [Soa]) are initialized.
+    cells: Box<UnsafeCell<[MaybeUninit<T>]>>,
+}
+
+impl<T> SoaColumn<T> {
+    /// Creates a new column with room for `capacity` fields, none of which are initialized.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let cells: Box<[MaybeUninit<T>]> = (0..capacity).map(|_| MaybeUninit::uninit()).collect();
+        // SAFETY: `UnsafeCell<[U]>` has the same in-memory representation as `[U]`.
+        let cells = unsafe { Box::from_raw(Box::into_raw(cells) as *mut UnsafeCell<[MaybeUninit<T>]>) };
+        Self { cells }
+    }
+
+    /// Returns a raw pointer to the first field of the column.
+    fn as_mut_ptr(&self) -> *mut T {
+        self.cells.get() as *mut T
+    }
+
+    /// Moves `value` into the field at `index`, without dropping the previous value of the field.
+    ///
+    /// # Safety
+    /// `index` must be less than the capacity of the column.
+    pub unsafe fn write(&mut self, index: usize, value: T) {
+        self.as_mut_ptr().add(index).write(value)
+    }
+
+    /// Moves the value out of the field at `index`, which leaves the field uninitialized.
+    ///
+    /// # Safety
+    /// `index` must be less than the capacity of the column and the field at `index` must be initialized.
+    pub unsafe fn read(&mut self, index: usize) -> T {
+        self.as_mut_ptr().add(index).read()
+    }
+
+    /// Returns a deferred reference to the first `len` fields of the column.
+    ///
+    /// # Safety
+    /// The first `len` fields of the column must be initialized and they must not be mutated while
+    /// the returned deferred reference is dereferenced.
+    pub unsafe fn slice(&self, len: usize) -> Deferred<&[T]> {
+        Deferred::from_raw_parts(self.as_mut_ptr(), len)
+    }
+
+    /// Returns a deferred mutable reference to the first `len` fields of the column.
+    ///
+    /// # Safety
+    /// The first `len` fields of the column must be initialized and they must not be accessed through any
+    /// other reference while the returned deferred mutable reference is dereferenced.
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn slice_mut(&self, len: usize) -> Deferred<&mut [T]> {
+        Deferred::from_raw_parts_mut(self.as_mut_ptr(), len)
+    }
+
+    /// Drops the first `len` fields of the column in place, which leaves them uninitialized.
+    ///
+    /// # Safety
+    /// The first `len` fields of the column must be initialized.
+    pub unsafe fn drop_in_place(&mut self, len: usize) {
+        core::ptr::drop_in_place(core::ptr::slice_from_raw_parts_mut(self.as_mut_ptr(), len))
+    }
+}
+
+/// A record type which can be stored in a [Soa] container, where each field is stored in its own [SoaColumn].
+/// This trait should be implemented with `#[derive(SoaRecord)]`, which is available with the `soa` feature.
+/// For a struct `Name`, the derive macro also generates:
+/// * `NameColumns`, which has a [`SoaColumn<FieldTy>`](SoaColumn) for each field;
+/// * `NameColumnsRef<'a>`, which has a [`Deferred<&'a [FieldTy]>`](crate::Deferred) for each field;
+/// * `NameColumnsMut<'a>`, which has a [`Deferred<&'a mut [FieldTy]>`](crate::Deferred) for each field.
+///
+/// The fields of the generated column structs have the same visibility as the fields of the record,
+/// and the `#[cfg(...)]` attributes of the fields of the record are forwarded to them.
+/// The derive macro does not support generic structs, tuple structs or structs without fields:
+/// ```compile_fail
+/// use deferred_reference::SoaRecord;
+/// #[derive(SoaRecord)]
+/// struct Pair<T> {
+///     left: T,
+///     right: T,
+/// }
+/// ```
+///
+/// # Safety
+/// The [Soa] relies on the implementation to store each field of the record in its own column, to move
+/// exactly the fields at `index` in [SoaRecord::write] and [SoaRecord::read], and to hand out deferred
+/// references to distinct columns in [SoaRecord::columns] and [SoaRecord::columns_mut].
+pub unsafe trait SoaRecord: Sized {
+    /// The columns of a [Soa] of this record type.
+    type Columns;
+    /// Deferred references to all columns at once, see [Soa::columns].
+    type ColumnsRef<'a>;
+    /// Deferred mutable references to all columns at once, see [Soa::columns_mut].
+    type ColumnsMut<'a>;
+
+    /// Creates columns with room for `capacity` fields each.
+    fn with_capacity(capacity: usize) -> Self::Columns;
+
+    /// Moves the fields of `record` into the columns at `index`.
+    ///
+    /// # Safety
+    /// `index` must be less than the capacity of the columns. The previous fields at `index` are not dropped.
+    unsafe fn write(columns: &mut Self::Columns, index: usize, record: Self);
+
+    /// Moves the fields at `index` out of the columns, which leaves them uninitialized.
+    ///
+    /// # Safety
+    /// `index` must be less than the capacity of the columns and the fields at `index` must be initialized.
+    unsafe fn read(columns: &mut Self::Columns, index: usize) -> Self;
+
+    /// Returns deferred references to the first `len` fields of all columns.
+    ///
+    /// # Safety
+    /// See [SoaColumn::slice].
+    unsafe fn columns(columns: &Self::Columns, len: usize) -> Self::ColumnsRef<'_>;
+
+    /// Returns deferred mutable references to the first `len` fields of all columns.
+    ///
+    /// # Safety
+    /// See [SoaColumn::slice_mut].
+    unsafe fn columns_mut(columns: &Self::Columns, len: usize) -> Self::ColumnsMut<'_>;
+
+    /// Drops the first `len` fields of all columns in place.
+    ///
+    /// # Safety
+    /// See [SoaColumn::drop_in_place].
+    unsafe fn drop_in_place(columns: &mut Self::Columns, len: usize);
+}
+
+/// The deferred references to the columns that a [Soa] has handed out since it was last borrowed mutably.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Views {
+    /// No deferred references to the columns have been handed out.
+    None,
+    /// Deferred references have been handed out by [Soa::columns].
+    Shared,
+    /// Deferred mutable references have been handed out by [Soa::columns_mut].
+    Mutable,
+}
+
+/// A fixed-capacity struct-of-arrays container, which stores each field of its records in a separate
+/// `UnsafeCell<[FieldTy]>` column (see [SoaColumn]). All columns are available at the same time as disjoint
+/// deferred mutable references (i.e. `Deferred<&mut [FieldTy]>`) through a shared reference `&self`, so that
+/// different systems (e.g. in an entity component system) can mutate one column while they read another,
+/// possibly from different threads. This replaces building such columns by hand from an [UnsafeCell] per
+/// column and [DeferMut](crate::DeferMut), and it needs no `unsafe` code. This requires the `soa` feature.
+///
+/// Just like [DeferredArena](crate::DeferredArena), the container hands out the deferred mutable references
+/// to its columns at most once (until the columns are [released](Soa::release_columns)), so they can not alias
+/// by construction. The deferred references borrow the container, which makes it impossible to release the
+/// columns, or to add or remove records, while any of them are still in use.
+///
+/// # Example
+/// ```
+/// use deferred_reference::{Soa, SoaRecord};
+/// #[derive(SoaRecord)]
+/// struct Particle {
+///     position: [f32; 2],
+///     velocity: [f32; 2],
+/// }
+/// let mut particles: Soa<Particle> = (0..100).map(|i| Particle {
+///     position: [0.0; 2],
+///     velocity: [i as f32, 1.0],
+/// }).collect();
+/// let world = &particles;
+/// let columns = world.columns_mut().unwrap();
+/// let (mut positions, velocities) = (columns.position, columns.velocity.into_ref());
+/// let speed = std::thread::scope(|scope| {
+///     // one system mutates the positions while the other system reads the velocities
+///     scope.spawn(move || positions.iter_mut().for_each(|position| position[1] += 1.0));
+///     scope.spawn(move || velocities.iter().map(|velocity| velocity[0]).sum::<f32>()).join().unwrap()
+/// });
+/// assert_eq!(4950.0, speed);
+/// assert!(world.columns().is_none()); // the columns have already been handed out
+/// particles.release_columns();
+/// assert_eq!(Some(&[0.0, 1.0]), particles.columns().unwrap().position.last());
+/// assert_eq!([99.0, 1.0], particles.pop().unwrap().velocity);
+/// ```
+pub struct Soa<R: SoaRecord> {
+    /// The columns of the records.
+    columns: R::Columns,
+    /// The number of records, i.e. the number of initialized fields in each column.
+    len: usize,
+    /// The number of fields that each column has room for.
+    capacity: usize,
+    /// The deferred references to the columns which have been handed out.
+    views: Cell<Views>,
+}
+
+impl<R: SoaRecord> Soa<R> {
+    /// Creates a new empty container with room for `capacity` records.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            columns: R::with_capacity(capacity),
+            len: 0,
+            capacity,
+            views: Cell::new(Views::None),
+        }
+    }
+
+    /// Returns the number of records.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if there are no records.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of records that the container has room for.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Appends a record to the back of the columns.
+    ///
+    /// # Errors
+    /// Returns the record back if the container is full.
+    pub fn push(&mut self, record: R) -> Result<(), R> {
+        if self.len == self.capacity {
+            return Err(record);
+        }
+        // SAFETY: the fields at `len` lie within the capacity and they are not initialized.
+        unsafe { R::write(&mut self.columns, self.len, record) };
+        self.len += 1;
+        self.release_columns();
+        Ok(())
+    }
+
+    /// Removes the last record and returns it, or returns `None` if there are no records.
+    pub fn pop(&mut self) -> Option<R> {
+        if self.len == 0 {
+            None
+        } else {
+            Some(self.swap_remove(self.len - 1))
+        }
+    }
+
+    /// Removes the record at `index` and returns it. The last record is moved into its place.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    #[track_caller]
+    pub fn swap_remove(&mut self, index: usize) -> R {
+        assert!(index < self.len, "index {} out of range for Soa of length {}", index, self.len);
+        self.len -= 1;
+        self.release_columns();
+        // SAFETY: the fields at `index` and at the old last index `len` are initialized. The fields at `len`
+        // are moved into the place of the removed fields, which leaves the fields at `len` uninitialized.
+        unsafe {
+            let record = R::read(&mut self.columns, index);
+            if index != self.len {
+                let last = R::read(&mut self.columns, self.len);
+                R::write(&mut self.columns, index, last);
+            }
+            record
+        }
+    }
+
+    /// Returns deferred references to all columns, or returns `None` if the deferred mutable
+    /// references to the columns have been handed out by [Soa::columns_mut].
+    pub fn columns(&self) -> Option<R::ColumnsRef<'_>> {
+        if self.views.get() == Views::Mutable {
+            return None;
+        }
+        self.views.set(Views::Shared);
+        // SAFETY: the first `len` fields are initialized and no deferred mutable references to them are handed out.
+        Some(unsafe { R::columns(&self.columns, self.len) })
+    }
+
+    /// Returns disjoint deferred mutable references to all columns, which can be used at the same time,
+    /// or returns `None` if any (mutable or immutable) deferred references to the columns have already
+    /// been handed out since the container was last borrowed mutably.
+    pub fn columns_mut(&self) -> Option<R::ColumnsMut<'_>> {
+        if self.views.get() != Views::None {
+            return None;
+        }
+        self.views.set(Views::Mutable);
+        // SAFETY: the first `len` fields are initialized and no other deferred references to them are handed out.
+        Some(unsafe { R::columns_mut(&self.columns, self.len) })
+    }
+
+    /// Releases all deferred references to the columns, so that they can be handed out again. All deferred
+    /// references that were handed out before must be out of use by now, which the mutable borrow guarantees.
+    /// Adding or removing records releases the columns as well.
+    pub fn release_columns(&mut self) {
+        *self.views.get_mut() = Views::None;
+    }
+}
+
+impl<R: SoaRecord> Drop for Soa<R> {
+    fn drop(&mut self) {
+        // SAFETY: the first `len` fields are initialized and they are never used again.
+        unsafe { R::drop_in_place(&mut self.columns, self.len) }
+    }
+}
+
+impl<R: SoaRecord> core::iter::FromIterator<R> for Soa<R> {
+    /// Collects the records into a container whose capacity equals the number of records.
+    fn from_iter<I: IntoIterator<Item = R>>(iter: I) -> Self {
+        let records: Vec<R> = iter.into_iter().collect();
+        let mut soa = Self::with_capacity(records.len());
+        for record in records {
+            // note: the container has room for all records, so this never returns an error.
+            let _ = soa.push(record);
+        }
+        soa
+    }
+}
+
+impl<R: SoaRecord> core::fmt::Debug for Soa<R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Soa").field("len", &self.len).field("capacity", &self.capacity).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::rc::Rc;
+    use std::vec::Vec;
+    use crate::{Soa, SoaRecord};
+
+    /// A record with fields whose types contain commas and arrows.
+    #[derive(SoaRecord)]
+    struct Record {
+        /// A documented field.
+        pub(crate) id: u32,
+        map: BTreeMap<u8, (u16, u32)>,
+        pub map_fn: fn(u8, u8) -> u8,
+    }
+
+    /// Returns a record with the given `id`.
+    fn record(id: u32) -> Record {
+        Record { id, map: BTreeMap::new(), map_fn: core::cmp::max }
+    }
+
+    #[test]
+    fn push_and_remove() {
+        let mut soa: Soa<Record> = (0..5).map(record).collect();
+        assert_eq!((5, 5), (soa.len(), soa.capacity()));
+        assert_eq!(Some(7), soa.push(record(7)).err().map(|record| record.id));
+        assert_eq!(1, soa.swap_remove(1).id);
+        assert_eq!(&[0, 4, 2, 3], &*soa.columns().unwrap().id);
+        assert_eq!(Some(3), soa.pop().map(|record| record.id));
+        for id in 5..7 {
+            assert!(soa.push(record(id)).is_ok());
+        }
+        assert_eq!(&[0, 4, 2, 5, 6], &*soa.columns().unwrap().id);
+        while soa.pop().is_some() {}
+        assert!(soa.is_empty());
+        assert!(soa.pop().is_none());
+        assert_eq!("Soa { len: 0, capacity: 5 }", std::format!("{:?}", soa));
+    }
+
+    #[test]
+    fn columns_mut() {
+        let soa: Soa<Record> = (0..3).map(record).collect();
+        let mut columns = soa.columns_mut().unwrap();
+        assert!(soa.columns_mut().is_none() && soa.columns().is_none());
+        for (id, map) in columns.id.iter_mut().zip(columns.map.iter_mut()) {
+            map.insert(*id as u8, (1, 2));
+            *id *= 10;
+        }
+        assert_eq!(7, (columns.map_fn.last().unwrap())(3, 7));
+        let mut soa = soa;
+        soa.release_columns();
+        let ids: Vec<u32> = soa.columns().unwrap().id.to_vec();
+        assert_eq!(alloc::vec![0, 10, 20], ids);
+        assert_eq!(Some(&(1, 2)), soa.columns().unwrap().map.last().unwrap().get(&2));
+        assert!(soa.columns_mut().is_none());
+    }
+
+    #[test]
+    fn drops_records() {
+        let rc = Rc::new(());
+        /// A record which holds on to a reference count.
+        #[derive(SoaRecord)]
+        struct Counted {
+            /// The reference count.
+            rc: Rc<()>,
+        }
+        let mut soa = Soa::with_capacity(4);
+        for _ in 0..3 {
+            assert!(soa.push(Counted { rc: rc.clone() }).is_ok());
+        }
+        drop(soa.swap_remove(0));
+        assert_eq!(3, Rc::strong_count(&rc));
+        drop(soa);
+        assert_eq!(1, Rc::strong_count(&rc));
+    }
+
+    /// A record with conditionally compiled fields.
+    #[derive(SoaRecord)]
+    struct Conditional {
+        id: u32,
+        #[cfg(test)]
+        enabled: u8,
+        #[cfg(not(test))]
+        disabled: NotAType,
+    }
+
+    #[test]
+    fn cfg_fields() {
+        let mut soa = Soa::with_capacity(1);
+        assert!(soa.push(Conditional { id: 1, enabled: 2 }).is_ok());
+        let columns = soa.columns().unwrap();
+        assert_eq!((&[1][..], &[2][..]), (&*columns.id, &*columns.enabled));
+    }
+
+    #[test]
+    #[should_panic]
+    fn swap_remove_out_of_bounds() {
+        let mut soa: Soa<Record> = Soa::with_capacity(1);
+        soa.swap_remove(0);
+    }
+}
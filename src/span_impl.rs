@@ -0,0 +1,261 @@
+//! This module contains method implementations for querying the memory span of a [Deferred].
+
+use core::ops::Range;
+
+use crate::{Deferred, PointerLayout, Reference, SliceLike};
+
+/// # Methods for querying the memory span of deferred references
+/// Every deferred reference covers a "span" of memory: a base address and a size in bytes.
+/// These methods compute and compare spans without dereferencing the pointers involved,
+/// which makes it possible to assert at runtime that deferred mutable references which are
+/// handed out (e.g. through [Deferred::clone_unchecked]) do not alias one another. The spans of deferred
+/// slices need the length of the slice (see [Deferred::len]), which is why the examples use arrays:
+/// ```
+/// use deferred_reference::Deferred;
+/// let buffer = [[0u8; 8]; 2];
+/// let deferred = Deferred::new(&buffer);
+/// let (left, right) = (Deferred::from(&deferred[0]), Deferred::from(&deferred[1]));
+/// assert!(!left.overlaps(&right));
+/// assert!(deferred.contains(&left) && deferred.contains(&right));
+/// assert_eq!(Some(8), right.offset_in(&deferred));
+/// ```
+impl<T> Deferred<T>
+where
+    T: Reference,
+    T::Target: PointerLayout,
+{
+    /// Returns the range of addresses that this deferred reference covers, i.e. the address
+    /// of the pointee up to (but excluding) the address right after the last byte of the pointee.
+    /// The span of a zero-sized pointee is an empty range.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let buffer = [0u32; 4];
+    /// let deferred = Deferred::new(&buffer);
+    /// let span = deferred.span();
+    /// assert_eq!(buffer.as_ptr() as usize, span.start);
+    /// assert_eq!(16, span.len());
+    /// ```
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    pub fn span(&self) -> Range<usize> {
+        let start = self.as_ptr() as *const u8 as usize;
+        // note: the invariant of `Deferred` guarantees that the size fits in an `isize`.
+        let size = PointerLayout::size(self.as_ptr()).unwrap_or(0);
+        start..start + size
+    }
+
+    /// Returns `true` if the span of this deferred reference shares at least one byte with the span
+    /// of `other`. Zero-sized pointees (e.g. empty slices) never overlap with anything, not even when
+    /// they lie within the span of `other`.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let buffer = [[0u8; 8]; 2];
+    /// let deferred = Deferred::new(&buffer);
+    /// let (first, second) = (Deferred::from(&deferred[0]), Deferred::from(&deferred[1]));
+    /// assert!(deferred.overlaps(&first) && second.overlaps(&deferred));
+    /// assert!(!first.overlaps(&second));
+    /// ```
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    pub fn overlaps<U>(&self, other: &Deferred<U>) -> bool
+    where
+        U: Reference,
+        U::Target: PointerLayout,
+    {
+        let (this, other) = (self.span(), other.span());
+        !this.is_empty() && !other.is_empty() && this.start < other.end && other.start < this.end
+    }
+
+    /// Returns `true` if the span of `other` lies entirely within the span of this deferred reference.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let buffer = [[0u8; 4]; 4];
+    /// let deferred = Deferred::new(&buffer);
+    /// let first = Deferred::from(&deferred[0]);
+    /// assert!(deferred.contains(&first));
+    /// assert!(!first.contains(&deferred));
+    /// ```
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    pub fn contains<U>(&self, other: &Deferred<U>) -> bool
+    where
+        U: Reference,
+        U::Target: PointerLayout,
+    {
+        let (this, other) = (self.span(), other.span());
+        this.start <= other.start && other.end <= this.end
+    }
+
+    /// Returns the offset in bytes of this deferred reference from the start of `parent`, or `None`
+    /// if this deferred reference does not lie entirely within `parent`.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let buffer = [0u32; 4];
+    /// let deferred = Deferred::new(&buffer);
+    /// let (first, third) = (Deferred::from(&deferred[0]), Deferred::from(&deferred[2]));
+    /// assert_eq!(Some(0), first.offset_in(&deferred));
+    /// assert_eq!(Some(8), third.offset_in(&deferred));
+    /// assert_eq!(None, deferred.offset_in(&third));
+    /// ```
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    pub fn offset_in<U>(&self, parent: &Deferred<U>) -> Option<usize>
+    where
+        U: Reference,
+        U::Target: PointerLayout,
+    {
+        if parent.contains(self) {
+            Some(self.span().start - parent.span().start)
+        } else {
+            None
+        }
+    }
+}
+
+/// # Methods for recovering the index of deferred references to slice elements
+impl<T> Deferred<T>
+where
+    T: Reference,
+    T::Target: Sized,
+{
+    /// Returns the index of the element that this deferred reference points to in `parent`,
+    /// or `None` if it does not point to an element of `parent`. This is the inverse of
+    /// indexing into the slice or array that `parent` points to. Because elements of
+    /// zero-sized types do not have distinct addresses, this always returns `None` for those.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let buffer = [0u32; 4];
+    /// let deferred = Deferred::new(&buffer);
+    /// let element: Deferred<&u32> = Deferred::from(&deferred[2]);
+    /// assert_eq!(Some(2), element.index_in(&deferred));
+    /// ```
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    pub fn index_in<U>(&self, parent: &Deferred<U>) -> Option<usize>
+    where
+        U: Reference,
+        U::Target: SliceLike<Element = T::Target> + PointerLayout,
+    {
+        let size = core::mem::size_of::<T::Target>();
+        match self.offset_in(parent) {
+            Some(offset) if size != 0 && offset % size == 0 => Some(offset / size),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::UnsafeCell;
+    use crate::{Defer, Deferred};
+
+    #[test]
+    fn span() {
+        let buffer = UnsafeCell::new([0u16; 8]);
+        let deferred = buffer.defer();
+        assert_eq!(buffer.get() as usize..buffer.get() as usize + 16, deferred.span());
+        let unit = ();
+        assert!(Deferred::new(&unit).span().is_empty());
+    }
+
+    #[cfg(feature = "slice_ptr_len")]
+    #[test]
+    fn disjoint_clones() {
+        use crate::DeferMut;
+        let buffer = UnsafeCell::new([0u8; 32]);
+        let mut deferred = unsafe { buffer.defer_mut() };
+        let (left, right) = deferred.split_at_mut(16);
+        let (mut left_clone, mut right_clone) = unsafe { (left.clone_unchecked(), right.clone_unchecked()) };
+        let (a, b) = left_clone.split_at_mut(8);
+        let (c, _) = right_clone.split_at_mut(8);
+        assert!(!a.overlaps(&b) && !b.overlaps(&c) && !a.overlaps(&c));
+        assert!(left.overlaps(&a) && left.contains(&b) && !left.contains(&c));
+        let clone = unsafe { deferred.clone_unchecked() };
+        assert!(clone.overlaps(&deferred) && clone.contains(&deferred) && deferred.contains(&clone));
+    }
+
+    #[test]
+    fn zero_sized() {
+        let buffer = [(); 4];
+        let deferred = Deferred::new(&buffer);
+        let element = Deferred::from(&deferred[1]);
+        assert!(!element.overlaps(&deferred));
+        assert!(deferred.contains(&element));
+        assert_eq!(None, element.index_in(&deferred));
+    }
+
+    #[test]
+    fn zero_sized_field() {
+        /// A struct with a zero-sized field between two non-empty fields.
+        #[repr(C)]
+        struct Fields {
+            /// A field before the zero-sized field.
+            first: u32,
+            /// The zero-sized field, which lies within the span of the struct.
+            marker: (),
+            /// A field after the zero-sized field.
+            second: u32,
+        }
+        let fields = Fields { first: 0, marker: (), second: 0 };
+        let (deferred, marker) = (Deferred::new(&fields), Deferred::new(&fields.marker));
+        assert!(deferred.contains(&marker));
+        assert!(!marker.overlaps(&deferred) && !deferred.overlaps(&marker));
+        assert!(deferred.overlaps(&Deferred::new(&fields.first)) && deferred.overlaps(&Deferred::new(&fields.second)));
+    }
+
+    #[cfg(feature = "slice_ptr_len")]
+    #[test]
+    fn empty_subslice() {
+        let buffer = [0u8; 10];
+        let (whole, empty) = (Deferred::new(&buffer[..]), Deferred::new(&buffer[5..5]));
+        assert!(whole.contains(&empty));
+        assert!(!empty.overlaps(&whole) && !whole.overlaps(&empty));
+    }
+
+    #[test]
+    fn offset_and_index() {
+        let buffer = [0u64; 4];
+        let deferred = Deferred::new(&buffer);
+        for i in 0..4 {
+            let element = Deferred::from(&deferred[i]);
+            assert_eq!(Some(i * 8), element.offset_in(&deferred));
+            assert_eq!(Some(i), element.index_in(&deferred));
+        }
+        let other = [0u64; 1];
+        let element = Deferred::from(&other[0]);
+        assert_eq!(None, element.offset_in(&deferred));
+        assert_eq!(None, element.index_in(&deferred));
+        // an element which does not start at an element boundary
+        let unaligned: Deferred<&u32> = unsafe { Deferred::from_raw((buffer.as_ptr() as *const u32).add(1)) };
+        assert_eq!(Some(4), unaligned.offset_in(&deferred));
+    }
+
+    #[cfg(feature = "slice_ptr_len")]
+    #[test]
+    fn slices() {
+        let buffer = UnsafeCell::new([0u32; 8]);
+        let deferred: Deferred<&[u32]> = buffer.defer().into();
+        let (left, right) = deferred.split_at(3);
+        assert_eq!(12, left.span().len());
+        assert_eq!(Some(12), right.offset_in(&deferred));
+        let element = Deferred::from(&right[1]);
+        assert_eq!(Some(4), element.index_in(&deferred));
+        assert_eq!(Some(1), element.index_in(&right));
+        assert_eq!(None, element.index_in(&left));
+    }
+}
@@ -0,0 +1,308 @@
+//! This module contains the [TypedArena] struct, which allocates values in slabs and hands out copyable [Handle]s to them.
+
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use std::vec::Vec;
+
+use crate::Deferred;
+
+/// The number of values in the first slab of a [TypedArena]. Every following slab is twice as large as the previous one.
+const FIRST_SLAB_LEN: usize = 16;
+
+/// Returns the slab and the offset within that slab of the value at `index`.
+fn locate(index: usize) -> (usize, usize) {
+    let bits = core::mem::size_of::<usize>() * 8;
+    let slab = bits - 1 - (index / FIRST_SLAB_LEN + 1).leading_zeros() as usize;
+    (slab, index - FIRST_SLAB_LEN * ((1 << slab) - 1))
+}
+
+/// An invariant lifetime, which brands a [TypedArena] and its [Handle]s.
+type Brand<'arena> = PhantomData<fn(&'arena ()) -> &'arena ()>;
+
+/// A unique invariant lifetime for a [TypedArena], which is only available inside of [ArenaBrand::scope].
+/// The brand can not be copied, so every `'arena` lifetime belongs to exactly one arena.
+pub struct ArenaBrand<'arena>(Brand<'arena>);
+
+impl ArenaBrand<'_> {
+    /// Creates a new brand with a unique `'arena` lifetime and passes it to `f`, which can use it
+    /// to create a [TypedArena] with [TypedArena::new].
+    pub fn scope<R, F>(f: F) -> R
+    where
+        F: for<'arena> FnOnce(ArenaBrand<'arena>) -> R,
+    {
+        f(ArenaBrand(PhantomData))
+    }
+}
+
+impl core::fmt::Debug for ArenaBrand<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("ArenaBrand")
+    }
+}
+
+/// A copyable handle to a value in a [TypedArena]. A handle can only be used with the arena that returned it,
+/// which is checked at compile time through the `'arena` lifetime (see [ArenaBrand]).
+pub struct Handle<'arena, T> {
+    /// The index of the value in the arena.
+    index: usize,
+    /// The brand of the arena.
+    _brand: Brand<'arena>,
+    /// The type of the value.
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<'_, T> {
+    /// Returns the index of the value in the arena, which is the number of values allocated before it.
+    /// This can be used to store data about the values in side tables.
+    pub fn index(self) -> usize {
+        self.index
+    }
+}
+
+impl<T> Clone for Handle<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<'_, T> {}
+
+impl<T> PartialEq for Handle<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for Handle<'_, T> {}
+
+impl<T> core::hash::Hash for Handle<'_, T> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.index.hash(state)
+    }
+}
+
+impl<T> core::fmt::Debug for Handle<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("Handle").field(&self.index).finish()
+    }
+}
+
+/// The error returned by [TypedArena::get_many_mut] when two of the handles refer to the same value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct OverlappingHandles {
+    /// The position of the first of the two handles in the array of handles.
+    pub first: usize,
+    /// The position of the second of the two handles in the array of handles.
+    pub second: usize,
+}
+
+impl core::fmt::Display for OverlappingHandles {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "handles {} and {} refer to the same value", self.first, self.second)
+    }
+}
+
+impl std::error::Error for OverlappingHandles {}
+
+/// A typed arena, which allocates values of type `T` in slabs and hands out copyable [Handle]s to them.
+/// The values are never moved after they have been allocated, and they are dropped together with the arena.
+///
+/// The arena offers simultaneous mutable access to multiple values through [TypedArena::get_many_mut],
+/// which checks at runtime that the handles refer to distinct values and returns a deferred mutable reference
+/// for each handle. This is useful for graph structures (e.g. the nodes of an intermediate representation of a compiler),
+/// where a node and its neighbours need to be mutated at the same time, without the memory and runtime overhead
+/// of a [RefCell](core::cell::RefCell) per node. This requires the `std` feature.
+///
+/// Every arena is branded with its own `'arena` lifetime, which is only available inside of [ArenaBrand::scope].
+/// Handles carry the same brand, so using a handle with another arena is a compile time error. The values
+/// can store handles to other values of the same arena, because their type may depend on the `'arena` lifetime.
+///
+/// # Example
+/// ```
+/// use deferred_reference::{ArenaBrand, Handle, TypedArena};
+/// struct Node<'arena> {
+///     value: u32,
+///     edges: Vec<Handle<'arena, Node<'arena>>>,
+/// }
+/// let sum = ArenaBrand::scope(|brand| {
+///     let mut arena = TypedArena::new(brand);
+///     let a = arena.alloc(Node { value: 1, edges: Vec::new() });
+///     let b = arena.alloc(Node { value: 2, edges: vec![a] });
+///     let c = arena.alloc(Node { value: 3, edges: vec![a, b] });
+///     // move the values of the neighbours of `c` into `c`:
+///     let [mut a, mut b, mut c] = arena.get_many_mut([a, b, c]).unwrap();
+///     c.value += core::mem::take(&mut a.value) + core::mem::take(&mut b.value);
+///     arena.iter().map(|node| node.value).sum::<u32>()
+/// });
+/// assert_eq!(6, sum);
+/// ```
+///
+/// Handles can not be used with another arena:
+/// ```compile_fail
+/// use deferred_reference::{ArenaBrand, TypedArena};
+/// ArenaBrand::scope(|first| {
+///     let mut first = TypedArena::new(first);
+///     let handle = first.alloc(1);
+///     ArenaBrand::scope(|second| {
+///         TypedArena::new(second).get(handle);
+///     });
+/// });
+/// ```
+pub struct TypedArena<'arena, T> {
+    /// The slabs of the arena. The slabs never grow beyond their initial capacity, so the values are never moved.
+    slabs: Vec<Vec<T>>,
+    /// The number of values in the arena.
+    len: usize,
+    /// The brand of the arena.
+    _brand: Brand<'arena>,
+}
+
+impl<'arena, T> TypedArena<'arena, T> {
+    /// Creates a new empty arena, which is branded with the `'arena` lifetime of `brand`.
+    pub fn new(brand: ArenaBrand<'arena>) -> Self {
+        Self {
+            slabs: Vec::new(),
+            len: 0,
+            _brand: brand.0,
+        }
+    }
+
+    /// Returns the number of values in the arena.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the arena has no values.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Moves `value` into the arena and returns a handle to it.
+    pub fn alloc(&mut self, value: T) -> Handle<'arena, T> {
+        let (slab, _) = locate(self.len);
+        if slab == self.slabs.len() {
+            self.slabs.push(Vec::with_capacity(FIRST_SLAB_LEN << slab));
+        }
+        self.slabs[slab].push(value);
+        let index = self.len;
+        self.len += 1;
+        Handle { index, _brand: PhantomData, _marker: PhantomData }
+    }
+
+    /// Returns a pointer to the value of `handle`, without creating any references to the value.
+    fn ptr(&mut self, handle: Handle<'arena, T>) -> *mut T {
+        let (slab, offset) = locate(handle.index);
+        // SAFETY: the handle was returned by this arena (see the brand), so the value lies within the slab.
+        unsafe { self.slabs[slab].as_mut_ptr().add(offset) }
+    }
+
+    /// Returns a reference to the value of `handle`.
+    pub fn get(&self, handle: Handle<'arena, T>) -> &T {
+        let (slab, offset) = locate(handle.index);
+        &self.slabs[slab][offset]
+    }
+
+    /// Returns a mutable reference to the value of `handle`.
+    pub fn get_mut(&mut self, handle: Handle<'arena, T>) -> &mut T {
+        let (slab, offset) = locate(handle.index);
+        &mut self.slabs[slab][offset]
+    }
+
+    /// Returns a deferred mutable reference to the value of each of the `handles`, after checking
+    /// that the handles refer to distinct values. The check compares every pair of handles, so this is intended
+    /// for a small number of handles (e.g. a node and its direct neighbours).
+    ///
+    /// # Errors
+    /// Returns [OverlappingHandles] if two of the handles refer to the same value.
+    pub fn get_many_mut<const N: usize>(&mut self, handles: [Handle<'arena, T>; N]) -> Result<[Deferred<&mut T>; N], OverlappingHandles> {
+        for second in 1..N {
+            if let Some(first) = handles[..second].iter().position(|&handle| handle == handles[second]) {
+                return Err(OverlappingHandles { first, second });
+            }
+        }
+        // SAFETY: an array of `MaybeUninit` does not need to be initialized.
+        let mut deferred: [MaybeUninit<Deferred<&mut T>>; N] = unsafe { MaybeUninit::uninit().assume_init() };
+        for (slot, &handle) in deferred.iter_mut().zip(handles.iter()) {
+            // SAFETY: the handles refer to distinct values, so the deferred references are disjoint,
+            // SAFETY: and the arena is mutably borrowed for as long as the deferred references are alive.
+            *slot = MaybeUninit::new(unsafe { Deferred::from_raw_mut(self.ptr(handle)) });
+        }
+        // SAFETY: all `N` elements have been initialized and `MaybeUninit<U>` has the same layout as `U`.
+        Ok(unsafe { (&deferred as *const [MaybeUninit<Deferred<&mut T>>; N] as *const [Deferred<&mut T>; N]).read() })
+    }
+
+    /// Returns an iterator over the values in the arena, in the order in which they were allocated.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + '_ {
+        self.slabs.iter().flatten()
+    }
+
+    /// Returns an iterator over mutable references to the values in the arena, in the order in which they were allocated.
+    pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut T> + '_ {
+        self.slabs.iter_mut().flatten()
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for TypedArena<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+    use crate::{ArenaBrand, OverlappingHandles, TypedArena};
+
+    #[test]
+    fn locate() {
+        assert_eq!((0, 0), super::locate(0));
+        assert_eq!((0, 15), super::locate(15));
+        assert_eq!((1, 0), super::locate(16));
+        assert_eq!((1, 31), super::locate(47));
+        assert_eq!((2, 0), super::locate(48));
+    }
+
+    #[test]
+    fn many_slabs() {
+        ArenaBrand::scope(|brand| {
+            let mut arena = TypedArena::new(brand);
+            let handles: Vec<_> = (0..1000).map(|value| arena.alloc(value)).collect();
+            let first = arena.get(handles[0]) as *const usize;
+            for &handle in &handles {
+                *arena.get_mut(handle) *= 2;
+            }
+            assert_eq!(first, arena.get(handles[0]));
+            assert!(handles.iter().all(|&handle| *arena.get(handle) == handle.index() * 2));
+            assert_eq!(999 * 1000, arena.iter().sum::<usize>());
+            assert_eq!(1000, arena.len());
+        });
+    }
+
+    #[test]
+    fn get_many_mut() {
+        ArenaBrand::scope(|brand| {
+            let mut arena = TypedArena::new(brand);
+            let (a, b, c) = (arena.alloc(1), arena.alloc(2), arena.alloc(3));
+            {
+                let [mut c, mut a] = arena.get_many_mut([c, a]).unwrap();
+                core::mem::swap(&mut *a, &mut *c);
+            }
+            assert_eq!(Err(OverlappingHandles { first: 0, second: 2 }), arena.get_many_mut([b, a, b]).map(|_| ()));
+            assert!(arena.get_many_mut([]).is_ok());
+            assert_eq!("[3, 2, 1]", std::format!("{:?}", arena));
+        });
+    }
+
+    #[test]
+    fn drop_values() {
+        let rc = std::rc::Rc::new(());
+        ArenaBrand::scope(|brand| {
+            let mut arena = TypedArena::new(brand);
+            for _ in 0..100 {
+                arena.alloc(rc.clone());
+            }
+            assert_eq!(101, std::rc::Rc::strong_count(&rc));
+        });
+        assert_eq!(1, std::rc::Rc::strong_count(&rc));
+    }
+}
@@ -0,0 +1,299 @@
+//! This module contains the [UnalignedDeferred] struct and the methods for unaligned access to deferred byte slices.
+
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+
+use crate::{Deferred, Pod, Reference, SliceLike, SlicePointerIndex};
+
+/// A deferred reference to a value which is not necessarily properly aligned, such as a field of a
+/// `#[repr(packed)]` struct or a value at an arbitrary offset in a byte buffer. Creating a reference
+/// to an unaligned value is undefined behavior, so unlike [Deferred] this type does not implement
+/// [Deref](core::ops::Deref). Instead, the value can only be accessed by value, through
+/// [UnalignedDeferred::read], [UnalignedDeferred::write] and [UnalignedDeferred::replace].
+///
+/// Just like [Deferred], this comes in two flavors: `UnalignedDeferred<&T>` and `UnalignedDeferred<&mut T>`.
+/// Unaligned deferred references to values in byte buffers are obtained with [Deferred::unaligned_at] and
+/// [Deferred::unaligned_at_mut]. Unaligned deferred references to fields of packed structs are obtained with
+/// [UnalignedDeferred::from_raw] and [UnalignedDeferred::from_raw_mut].
+///
+/// # Example
+/// ```
+/// use deferred_reference::{Deferred, UnalignedDeferred};
+/// #[repr(C, packed)]
+/// struct Header {
+///     kind: u8,
+///     len: u32,
+/// }
+/// let mut header = Header { kind: 1, len: 2 };
+/// let mut deferred = Deferred::from(&mut header);
+/// // SAFETY: the field lies within `header`, which outlives `len`.
+/// let mut len = unsafe {
+///     UnalignedDeferred::from_raw_mut(core::ptr::addr_of_mut!((*deferred.as_mut_ptr()).len))
+/// };
+/// assert_eq!(2, len.read());
+/// len.write(3);
+/// assert_eq!(3, { header.len });
+/// ```
+pub struct UnalignedDeferred<T>
+where
+    T: Reference,
+    T::Target: Sized,
+{
+    /// A pointer to the (possibly unaligned) value.
+    ptr: NonNull<T::Target>,
+    /// Inherits the variance, auto traits and lifetime from the reference type.
+    _marker: PhantomData<T>,
+}
+
+// SAFETY: this is safe, because we merely inherit the Sync trait bounds from the Rust reference types.
+unsafe impl<T: Sync + Reference> Sync for UnalignedDeferred<T> where T::Target: Sized {}
+
+// SAFETY: this is safe, because we merely inherit the Send trait bounds from the Rust reference types.
+unsafe impl<T: Send + Reference> Send for UnalignedDeferred<T> where T::Target: Sized {}
+
+impl<T> Copy for UnalignedDeferred<&T> {}
+impl<T> Clone for UnalignedDeferred<&T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> UnalignedDeferred<&T> {
+    /// Constructs an unaligned deferred immutable reference from a raw pointer, which does not need to be aligned.
+    ///
+    /// # Safety
+    /// The caller must uphold the same guarantees as for [Deferred::from_raw], except that `ptr`
+    /// does not need to be properly aligned.
+    pub unsafe fn from_raw(ptr: *const T) -> Self {
+        debug_assert!(!ptr.is_null(), "UnalignedDeferred::from_raw: pointer must be non-null");
+        Self {
+            ptr: NonNull::new_unchecked(ptr as *mut T),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> UnalignedDeferred<&'a mut T> {
+    /// Constructs an unaligned deferred mutable reference from a raw pointer, which does not need to be aligned.
+    ///
+    /// # Safety
+    /// The caller must uphold the same guarantees as for [Deferred::from_raw_mut], except that `ptr`
+    /// does not need to be properly aligned.
+    pub unsafe fn from_raw_mut(ptr: *mut T) -> Self {
+        debug_assert!(!ptr.is_null(), "UnalignedDeferred::from_raw_mut: pointer must be non-null");
+        Self {
+            ptr: NonNull::new_unchecked(ptr),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Converts this unaligned deferred mutable reference into an unaligned deferred immutable reference.
+    pub fn into_ref(self) -> UnalignedDeferred<&'a T> {
+        UnalignedDeferred {
+            ptr: self.ptr,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> UnalignedDeferred<T>
+where
+    T: Reference,
+    T::Target: Sized,
+{
+    /// Returns a raw pointer to the (possibly unaligned) value.
+    pub fn as_ptr(&self) -> *const T::Target {
+        self.ptr.as_ptr()
+    }
+
+    /// Reads a copy of the value, without creating a reference to it.
+    pub fn read(&self) -> T::Target
+    where
+        T::Target: Copy,
+    {
+        // SAFETY: the pointer is valid for reads, it only needs to be read without alignment.
+        unsafe { core::ptr::read_unaligned(self.as_ptr()) }
+    }
+}
+
+impl<T> UnalignedDeferred<&mut T> {
+    /// Returns a raw mutable pointer to the (possibly unaligned) value.
+    pub fn as_mut_ptr(&self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+
+    /// Replaces the value with `value` and returns the old value, without creating a reference to it.
+    pub fn replace(&mut self, value: T) -> T {
+        // SAFETY: the pointer is valid for reads and writes, it only needs to be accessed without alignment.
+        unsafe {
+            let old = core::ptr::read_unaligned(self.as_mut_ptr());
+            core::ptr::write_unaligned(self.as_mut_ptr(), value);
+            old
+        }
+    }
+
+    /// Overwrites the value with `value`, without creating a reference to it. The old value is dropped.
+    pub fn write(&mut self, value: T) {
+        drop(self.replace(value));
+    }
+}
+
+impl<T: Reference> From<Deferred<T>> for UnalignedDeferred<T>
+where
+    T::Target: Sized,
+{
+    fn from(deferred: Deferred<T>) -> Self {
+        Self {
+            // SAFETY: the pointer is derived from a non-null pointer.
+            ptr: unsafe { NonNull::new_unchecked(deferred.as_ptr() as *mut T::Target) },
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> core::fmt::Debug for UnalignedDeferred<T>
+where
+    T: Reference,
+    T::Target: Copy + core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("UnalignedDeferred").field(&self.read()).finish()
+    }
+}
+
+/// # Methods for unaligned access to deferred references to byte slices and byte arrays
+/// These methods access values of [Pod] types at arbitrary byte offsets, e.g. the fields of a wire-format
+/// header inside a shared packet buffer. Only the bytes of the accessed value are touched, so different
+/// values in the same buffer can be accessed at the same time.
+impl<T> Deferred<T>
+where
+    T: Reference,
+    T::Target: SliceLike<Element = u8>,
+{
+    /// Returns an unaligned deferred immutable reference to the value of type `U` at byte offset `offset`.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let packet = [0xffu8, 0x01, 0x00, 0x00, 0x00];
+    /// let deferred = Deferred::new(&packet);
+    /// let field = deferred.unaligned_at::<u32>(1);
+    /// assert_eq!(u32::from_ne_bytes([0x01, 0x00, 0x00, 0x00]), field.read());
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if the value does not lie within the array or slice.
+    /// For slices this method also needs the length of the slice. See [Deferred::len] for when this can panic.
+    #[track_caller]
+    pub fn unaligned_at<U: Pod>(&self, offset: usize) -> UnalignedDeferred<&U> {
+        let end = offset.saturating_add(core::mem::size_of::<U>());
+        let bytes = (offset..end).index(self.as_ptr());
+        // SAFETY: the bytes of the value lie within the array or slice, and `U` is `Pod`.
+        unsafe { UnalignedDeferred::from_raw(bytes as *const U) }
+    }
+
+    /// Reads a copy of the value of type `U` at byte offset `offset`, without creating any references.
+    ///
+    /// # Panics
+    /// Panics if the value does not lie within the array or slice.
+    /// For slices this method also needs the length of the slice. See [Deferred::len] for when this can panic.
+    #[track_caller]
+    pub fn read_unaligned<U: Pod>(&self, offset: usize) -> U {
+        self.unaligned_at(offset).read()
+    }
+}
+
+/// # Methods for unaligned access to deferred _mutable_ references to byte slices and byte arrays
+impl<T> Deferred<&mut T>
+where
+    T: SliceLike<Element = u8> + ?Sized,
+{
+    /// Returns an unaligned deferred mutable reference to the value of type `U` at byte offset `offset`.
+    ///
+    /// # Panics
+    /// Panics if the value does not lie within the array or slice.
+    /// For slices this method also needs the length of the slice. See [Deferred::len] for when this can panic.
+    #[track_caller]
+    pub fn unaligned_at_mut<U: Pod>(&mut self, offset: usize) -> UnalignedDeferred<&mut U> {
+        let end = offset.saturating_add(core::mem::size_of::<U>());
+        let bytes = (offset..end).index_mut(self.as_mut_ptr());
+        // SAFETY: the bytes of the value lie within the array or slice, and `U` is `Pod`.
+        unsafe { UnalignedDeferred::from_raw_mut(bytes as *mut U) }
+    }
+
+    /// Writes `value` at byte offset `offset`, without creating any references.
+    ///
+    /// # Example
+    /// ```
+    /// use deferred_reference::Deferred;
+    /// let mut packet = [0u8; 6];
+    /// let mut deferred = Deferred::from(&mut packet);
+    /// deferred.write_unaligned(1, 0x0102_0304u32.to_be());
+    /// assert_eq!([0, 1, 2, 3, 4, 0], packet);
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if the value does not lie within the array or slice.
+    /// For slices this method also needs the length of the slice. See [Deferred::len] for when this can panic.
+    #[track_caller]
+    pub fn write_unaligned<U: Pod>(&mut self, offset: usize, value: U) {
+        self.unaligned_at_mut(offset).write(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::UnsafeCell;
+    use crate::{DeferMut, Deferred, UnalignedDeferred};
+
+    #[repr(C, packed)]
+    #[derive(Clone, Copy)]
+    struct Packed {
+        tag: u8,
+        value: u64,
+    }
+
+    #[test]
+    fn packed_field() {
+        let packed = UnsafeCell::new(Packed { tag: 1, value: 2 });
+        let deferred = unsafe { packed.defer_mut() };
+        let mut value = unsafe { UnalignedDeferred::from_raw_mut(core::ptr::addr_of_mut!((*deferred.as_mut_ptr()).value)) };
+        let tag = unsafe { UnalignedDeferred::from_raw(core::ptr::addr_of!((*deferred.as_ptr()).tag)) };
+        assert_eq!(2, value.replace(3));
+        assert_eq!(1, tag.read());
+        assert_eq!("UnalignedDeferred(3)", alloc::format!("{:?}", value.into_ref()));
+        assert_eq!(3, { unsafe { *packed.get() }.value });
+    }
+
+    #[test]
+    fn bytes() {
+        let buffer = UnsafeCell::new([0u8; 16]);
+        let mut deferred = unsafe { buffer.defer_mut() };
+        let mut other = unsafe { deferred.clone_unchecked() };
+        // two fields of the same buffer are accessed at the same time
+        let mut a = deferred.unaligned_at_mut::<u32>(1);
+        let mut b = other.unaligned_at_mut::<u64>(5);
+        a.write(u32::MAX);
+        b.write(u64::MAX);
+        assert_eq!(u32::MAX, a.read() & b.read() as u32);
+        assert_eq!(u64::MAX, deferred.read_unaligned(5));
+        deferred.write_unaligned(13, [1u8, 2, 3]);
+        assert_eq!([0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 1, 2, 3], unsafe { *buffer.get() });
+        assert_eq!(0, deferred.read_unaligned::<u8>(0));
+        assert_eq!(0, deferred.read_unaligned::<[u8; 0]>(16).len());
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_bounds() {
+        let buffer = [0u8; 8];
+        Deferred::new(&buffer).read_unaligned::<u32>(5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn offset_overflow() {
+        let buffer = [0u8; 8];
+        Deferred::new(&buffer).read_unaligned::<u32>(usize::MAX);
+    }
+}
@@ -0,0 +1,292 @@
+//! This module contains the [WriteOnly] deferred reference and the [DeferWriteOnly] trait.
+
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+
+use crate::{Deferred, PointerLength, SliceLike, SlicePointerIndex};
+
+/// A write-only deferred reference, which can only be used to store values and which can never be dereferenced.
+/// Unlike [`Deferred<&mut T>`](Deferred), this never creates a reference to the place it points to, not even a shared one,
+/// so it may point to uninitialized memory or to memory which is owned by a device (e.g. DMA descriptors or framebuffers).
+///
+/// Write-only references are a separate type instead of a third flavor of [Deferred], because the generic methods
+/// and trait implementations of [Deferred] (e.g. [Index](core::ops::Index), [Debug](core::fmt::Debug) and
+/// [Deferred::split_at]) read from the place, which would be undefined behavior for write-only memory.
+///
+/// A [WriteOnly] can be obtained from a mutable reference, from a [`Deferred<&mut T>`](Deferred),
+/// from a type which implements the [DeferWriteOnly] trait or from a raw pointer with [WriteOnly::from_raw].
+///
+/// # Example
+/// ```
+/// use deferred_reference::DeferWriteOnly;
+/// use core::cell::UnsafeCell;
+/// // a framebuffer which is scanned out by a device
+/// let frame = UnsafeCell::new([0u32; 64]);
+/// // SAFETY: nothing else accesses `frame` while `pixels` is in use.
+/// let mut pixels = unsafe { frame.defer_write_only() };
+/// // note: on arrays this works without the `slice_ptr_len` feature, too, but on slices
+/// // (e.g. after `WriteOnly::split_at_mut`) these methods need the length of the slice.
+/// pixels.fill(0xff00_00ff);
+/// pixels.write_volatile_at(63, 0x00ff_00ff);
+/// assert_eq!(0x00ff_00ff, frame.into_inner()[63]);
+/// ```
+#[repr(transparent)]
+pub struct WriteOnly<'a, T: ?Sized> {
+    /// The pointer to the place, which is valid for writes but not necessarily for reads.
+    ptr: NonNull<T>,
+    /// Inherits the variance, auto traits and lifetime from a mutable reference.
+    _marker: PhantomData<&'a mut T>,
+}
+
+// SAFETY: this is safe, because we merely inherit the Send trait bounds from a mutable reference.
+unsafe impl<T: Send + ?Sized> Send for WriteOnly<'_, T> {}
+
+// SAFETY: this is safe, because we merely inherit the Sync trait bounds from a mutable reference.
+unsafe impl<T: Sync + ?Sized> Sync for WriteOnly<'_, T> {}
+
+impl<'a, T: ?Sized> WriteOnly<'a, T> {
+    /// Constructs a write-only deferred reference from a raw pointer.
+    ///
+    /// # Safety
+    /// The pointer must be non-null, properly aligned and valid for writes (but not necessarily for reads)
+    /// for the duration of `'a`. The place does not need to be initialized. Nothing else may access the place
+    /// while it is being written to through the returned [WriteOnly].
+    pub unsafe fn from_raw(ptr: *mut T) -> Self {
+        debug_assert!(!(ptr as *mut u8).is_null(), "WriteOnly::from_raw: pointer must be non-null");
+        Self {
+            ptr: NonNull::new_unchecked(ptr),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns a raw mutable pointer to the place.
+    pub fn as_mut_ptr(&self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+
+    /// Reborrows this write-only deferred reference for a shorter lifetime.
+    pub fn reborrow(&mut self) -> WriteOnly<'_, T> {
+        WriteOnly {
+            ptr: self.ptr,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Converts this write-only deferred reference into a deferred mutable reference, which can be dereferenced.
+    ///
+    /// # Safety
+    /// The place must be initialized and it must be valid for reads, see the invariant of [Deferred].
+    pub unsafe fn into_deferred(self) -> Deferred<&'a mut T> {
+        Deferred::from_raw_mut(self.as_mut_ptr())
+    }
+}
+
+impl<T> WriteOnly<'_, T> {
+    /// Stores `value` in the place. The old value is not dropped, because it may not be initialized.
+    pub fn write(&mut self, value: T) {
+        // SAFETY: the pointer is valid for writes.
+        unsafe { self.as_mut_ptr().write(value) }
+    }
+
+    /// Performs a volatile store of `value` in the place, e.g. for memory-mapped device registers.
+    /// The old value is not dropped, because it may not be initialized.
+    pub fn write_volatile(&mut self, value: T) {
+        // SAFETY: the pointer is valid for writes.
+        unsafe { self.as_mut_ptr().write_volatile(value) }
+    }
+}
+
+/// # Methods only available for write-only deferred references to slices and arrays
+impl<T> WriteOnly<'_, T>
+where
+    T: SliceLike + ?Sized,
+{
+    /// Returns the number of elements in the array or slice.
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    pub fn len(&self) -> usize {
+        PointerLength::len(self.as_mut_ptr())
+    }
+
+    /// Returns `true` if the array or slice has a length of 0.
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Stores `value` in the element at `index`. The old value is not dropped, because it may not be initialized.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    /// For slices this method also needs the length of the slice. See [Deferred::len] for when this can panic.
+    #[track_caller]
+    pub fn write_at(&mut self, index: usize, value: T::Element) {
+        // SAFETY: the element lies within the array or slice, so it is valid for writes.
+        unsafe { index.index_mut(self.as_mut_ptr()).write(value) }
+    }
+
+    /// Performs a volatile store of `value` in the element at `index`.
+    /// The old value is not dropped, because it may not be initialized.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    /// For slices this method also needs the length of the slice. See [Deferred::len] for when this can panic.
+    #[track_caller]
+    pub fn write_volatile_at(&mut self, index: usize, value: T::Element) {
+        // SAFETY: the element lies within the array or slice, so it is valid for writes.
+        unsafe { index.index_mut(self.as_mut_ptr()).write_volatile(value) }
+    }
+
+    /// Stores copies of the elements of `src` in the elements starting at `offset`.
+    /// The old values are not dropped, because they may not be initialized.
+    ///
+    /// # Panics
+    /// Panics if the elements do not lie within the array or slice.
+    /// For slices this method also needs the length of the slice. See [Deferred::len] for when this can panic.
+    #[track_caller]
+    pub fn write_slice(&mut self, offset: usize, src: &[T::Element])
+    where
+        T::Element: Copy,
+    {
+        let dst = (offset..offset.saturating_add(src.len())).index_mut(self.as_mut_ptr()) as *mut T::Element;
+        // SAFETY: the elements lie within the array or slice and they can not overlap with `src`,
+        // SAFETY: because `src` can not be borrowed while `self` is mutably borrowed.
+        unsafe { core::ptr::copy_nonoverlapping(src.as_ptr(), dst, src.len()) }
+    }
+
+    /// Stores copies of `value` in all elements of the array or slice.
+    /// The old values are not dropped, because they may not be initialized.
+    ///
+    /// # Panics
+    /// For slices this method needs the length of the slice. See [Deferred::len] for when this can panic.
+    pub fn fill(&mut self, value: T::Element)
+    where
+        T::Element: Copy,
+    {
+        let ptr = self.as_mut_ptr() as *mut T::Element;
+        for index in 0..self.len() {
+            // SAFETY: the element lies within the array or slice, so it is valid for writes.
+            unsafe { ptr.add(index).write(value) }
+        }
+    }
+
+    /// Splits the array or slice into two disjoint write-only deferred references at index `mid`.
+    ///
+    /// # Panics
+    /// Panics if `mid > len`.
+    /// For slices this method also needs the length of the slice. See [Deferred::len] for when this can panic.
+    #[track_caller]
+    #[allow(clippy::type_complexity)]
+    pub fn split_at_mut(&mut self, mid: usize) -> (WriteOnly<'_, [T::Element]>, WriteOnly<'_, [T::Element]>) {
+        let ptr = self.as_mut_ptr();
+        let left = (..mid).index_mut(ptr);
+        // SAFETY: `..mid` lies within the array or slice, so `mid..` can not panic anymore.
+        // SAFETY: both halves are disjoint and they are derived from a valid pointer.
+        unsafe { (WriteOnly::from_raw(left), WriteOnly::from_raw((mid..).index_mut(ptr))) }
+    }
+}
+
+impl<'a, T: ?Sized> From<&'a mut T> for WriteOnly<'a, T> {
+    fn from(reference: &'a mut T) -> Self {
+        // SAFETY: a mutable reference is valid for writes and exclusive for `'a`.
+        unsafe { WriteOnly::from_raw(reference) }
+    }
+}
+
+impl<'a, T: ?Sized> From<Deferred<&'a mut T>> for WriteOnly<'a, T> {
+    fn from(deferred: Deferred<&'a mut T>) -> Self {
+        // SAFETY: the invariant of `Deferred` guarantees that the pointer is valid for writes for `'a`.
+        unsafe { WriteOnly::from_raw(deferred.as_mut_ptr()) }
+    }
+}
+
+impl<T: ?Sized> core::fmt::Debug for WriteOnly<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // the place can not be read, so only the address is printed
+        f.debug_tuple("WriteOnly").field(&(self.as_mut_ptr() as *mut u8)).finish()
+    }
+}
+
+/// The [DeferWriteOnly] trait offers easy access to write-only deferred references ([WriteOnly])
+/// to types that implement this trait, such as [UnsafeCell].
+///
+/// # Safety
+/// The same rules apply as for the [DeferMut](crate::DeferMut) trait, except that the place that the returned
+/// [WriteOnly] points to does not need to be initialized.
+pub unsafe trait DeferWriteOnly {
+    /// The type that the write-only deferred reference points to.
+    type Target: ?Sized;
+
+    /// Obtain a write-only deferred reference to [DeferWriteOnly::Target].
+    ///
+    /// # Safety
+    /// This method is unsafe, because it is possible to call it more than once. The caller must ensure that
+    /// nothing else accesses the place while it is being written to through the returned [WriteOnly].
+    unsafe fn defer_write_only(&self) -> WriteOnly<'_, Self::Target>;
+}
+
+unsafe impl<T: ?Sized> DeferWriteOnly for UnsafeCell<T> {
+    type Target = T;
+
+    unsafe fn defer_write_only(&self) -> WriteOnly<'_, T> {
+        WriteOnly::from_raw(self.get())
+    }
+}
+
+unsafe impl<T: ?Sized> DeferWriteOnly for Deferred<&mut T> {
+    type Target = T;
+
+    unsafe fn defer_write_only(&self) -> WriteOnly<'_, T> {
+        WriteOnly::from_raw(self.as_mut_ptr())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::UnsafeCell;
+    use core::mem::MaybeUninit;
+    use crate::{DeferWriteOnly, Deferred, WriteOnly};
+
+    #[test]
+    fn uninit_value() {
+        let value = UnsafeCell::new(MaybeUninit::<[u8; 4]>::uninit());
+        let mut write_only = unsafe { value.defer_write_only() };
+        write_only.write(MaybeUninit::new([1, 2, 3, 4]));
+        write_only.write_volatile(MaybeUninit::new([4, 3, 2, 1]));
+        assert_eq!([4, 3, 2, 1], unsafe { value.into_inner().assume_init() });
+    }
+
+    #[test]
+    fn indexed_stores() {
+        let mut buffer = [0u16; 8];
+        let mut write_only = WriteOnly::from(&mut buffer);
+        assert_eq!(8, write_only.len());
+        write_only.fill(9);
+        write_only.write_at(0, 1);
+        write_only.write_volatile_at(7, 7);
+        write_only.write_slice(2, &[2, 3]);
+        assert_eq!([1, 9, 2, 3, 9, 9, 9, 7], buffer);
+    }
+
+    #[test]
+    fn from_deferred() {
+        let mut value = 1u64;
+        let deferred = Deferred::from(&mut value);
+        let mut write_only = unsafe { deferred.defer_write_only() };
+        write_only.reborrow().write(2);
+        let mut deferred = unsafe { WriteOnly::from(deferred).into_deferred() };
+        *deferred += 1;
+        assert_eq!(3, value);
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_bounds() {
+        let mut buffer = [0u8; 4];
+        WriteOnly::from(&mut buffer).write_at(4, 0);
+    }
+}